@@ -0,0 +1,538 @@
+//! captures of raw kernel statistics, for later analysis.
+//!
+//! a capture holds each raw read of `/proc/stat`, exactly as the kernel wrote it, along with when
+//! it was read. each read can be rebuilt into the [`Snapshot`] that tach would have taken from it;
+//! see [`Frame::snapshot()`].
+//!
+//! this is used by the `tach` binary, and is not part of the library's stable api.
+//!
+//! # format, version 1
+//!
+//! a capture is a header, a blank line, and then a frame for each read:
+//!
+//! ```text
+//! tach-capture 1
+//! tick_rate 100
+//! host box
+//!
+//! frame 1234500000000 1704110400000000000 1423
+//! cpu  2255 34 2290 22625563 6290 127 456 0 0 0
+//! ...
+//! frame 1235500000000 1704110401000000000 1423
+//! ...
+//! ```
+//!
+//! the header holds a `key value` pair per line: the system's clock tick rate, and its hostname.
+//! readers should ignore keys that they do not know.
+//!
+//! each frame begins with a line holding the monotonic time at which the read began, and the
+//! wall-clock time, both in nanoseconds. the monotonic time is measured from an arbitrary point,
+//! such as when the system booted, so only differences between frames are meaningful. the wall
+//! time is measured from the unix epoch. the last number is the length of the raw statistics, in
+//! bytes, which follow immediately.
+
+use {
+    crate::{
+        source::{Clock, StatsSource},
+        stat::{Snapshot, StatReadError, TickRate},
+    },
+    std::{
+        fs::{self, File},
+        io::{self, BufRead, Read, Write},
+        path::{Path, PathBuf},
+        time::{Duration, Instant, SystemTime},
+    },
+};
+
+#[cfg(test)]
+mod tests;
+
+/// the first line of every capture.
+const MAGIC: &str = "tach-capture 1";
+
+/// a description of the system that a capture was taken on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Header {
+    /// the number of clock ticks in a second.
+    tick_rate: TickRate,
+    /// the system's hostname.
+    host: String,
+}
+
+/// a raw read of kernel statistics, and when it was taken.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Frame {
+    /// the monotonic time at which the read began, measured from an arbitrary point.
+    monotonic: Duration,
+    /// the wall-clock time at which the read began.
+    wall: SystemTime,
+    /// the statistics, exactly as they were read.
+    stats: Vec<u8>,
+}
+
+/// when a capture file is rotated, and how much space rotated files may use.
+///
+/// the file being written is renamed with a numbered suffix, such as `capture.3`, when it is
+/// rotated, and a new file is started in its place. higher numbers are newer.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Rotation {
+    /// rotate before a file grows beyond this many bytes.
+    size: Option<u64>,
+    /// rotate files once they have been written for this long.
+    every: Option<Duration>,
+    /// delete the oldest rotated files, so that all files use no more than this many bytes.
+    max_total: Option<u64>,
+}
+
+/// writes frames to a capture file, rotating it as needed.
+pub struct Writer {
+    /// the path of the file being written.
+    path: PathBuf,
+    /// the header written at the start of each file.
+    header: Header,
+    /// when the file is rotated.
+    rotation: Rotation,
+    /// the file being written.
+    file: File,
+    /// the size of the file being written, in bytes.
+    size: u64,
+    /// whether the file being written holds any frames.
+    frames: bool,
+    /// when the file being written was opened.
+    opened: Instant,
+}
+
+/// reads frames from a capture.
+pub struct Reader<R> {
+    reader: R,
+    header: Header,
+}
+
+/// a clock that is stopped at a single instant.
+struct Stopped(Instant);
+
+// === impl Header ===
+
+impl Header {
+    /// returns the header of a capture taken on this system, at the given tick rate.
+    pub fn new(tick_rate: TickRate) -> Self {
+        Self {
+            tick_rate,
+            host: crate::output::hostname(),
+        }
+    }
+
+    /// names the system `host`, rather than using this system's hostname.
+    pub fn with_host(self, host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            ..self
+        }
+    }
+
+    /// returns the number of clock ticks in a second.
+    pub fn tick_rate(&self) -> TickRate {
+        self.tick_rate
+    }
+
+    /// returns the system's hostname.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// encodes the header, and the blank line that ends it.
+    fn encode(&self) -> Vec<u8> {
+        let Self { tick_rate, host } = self;
+        format!(
+            "{MAGIC}\ntick_rate {}\nhost {}\n\n",
+            tick_rate.get(),
+            host.trim()
+        )
+        .into_bytes()
+    }
+}
+
+// === impl Frame ===
+
+impl Frame {
+    /// reads the statistics from `source`, noting when the read began.
+    pub fn read(source: &impl StatsSource) -> io::Result<Self> {
+        let monotonic = monotonic();
+        let wall = SystemTime::now();
+
+        let mut stats = Vec::new();
+        source.open()?.read_to_end(&mut stats)?;
+
+        Ok(Self {
+            monotonic,
+            wall,
+            stats,
+        })
+    }
+
+    /// parses the statistics into a snapshot, as tach would have when they were read.
+    ///
+    /// the snapshot's time is `base` plus the frame's monotonic time. passing the same `base` for
+    /// each frame of a capture preserves the time between them.
+    pub fn snapshot(&self, base: Instant) -> Result<Snapshot, StatReadError> {
+        Snapshot::read(self, &Stopped(base + self.monotonic))
+    }
+
+    /// returns the monotonic time at which the read began, measured from an arbitrary point.
+    pub fn monotonic(&self) -> Duration {
+        self.monotonic
+    }
+
+    /// returns the wall-clock time at which the read began.
+    pub fn wall(&self) -> SystemTime {
+        self.wall
+    }
+
+    /// returns the statistics, exactly as they were read.
+    pub fn stats(&self) -> &[u8] {
+        &self.stats
+    }
+
+    /// encodes the frame's line, and its statistics.
+    fn encode(&self) -> Vec<u8> {
+        let Self {
+            monotonic,
+            wall,
+            stats,
+        } = self;
+        let wall = wall
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut frame = format!(
+            "frame {} {} {}\n",
+            monotonic.as_nanos(),
+            wall.as_nanos(),
+            stats.len()
+        )
+        .into_bytes();
+        frame.extend_from_slice(stats);
+        frame
+    }
+}
+
+impl StatsSource for Frame {
+    fn open(&self) -> io::Result<impl Read> {
+        Ok(self.stats.as_slice())
+    }
+}
+
+// === impl Rotation ===
+
+impl Rotation {
+    /// rotates files before they grow beyond `bytes`.
+    pub fn with_size(self, bytes: u64) -> Self {
+        Self {
+            size: Some(bytes),
+            ..self
+        }
+    }
+
+    /// rotates files once they have been written for `every`.
+    pub fn with_every(self, every: Duration) -> Self {
+        Self {
+            every: Some(every),
+            ..self
+        }
+    }
+
+    /// deletes the oldest rotated files, so that all files use no more than `bytes`.
+    ///
+    /// files are also rotated before they grow beyond a quarter of `bytes`, so that the bound
+    /// can be kept by deleting rotated files.
+    pub fn with_max_total(self, bytes: u64) -> Self {
+        Self {
+            max_total: Some(bytes),
+            ..self
+        }
+    }
+
+    /// returns the size that a file may not grow beyond, if there is one.
+    fn limit(&self) -> Option<u64> {
+        let quarter = self.max_total.map(|bytes| bytes / 4);
+        match (self.size, quarter) {
+            (Some(size), Some(quarter)) => Some(size.min(quarter)),
+            (size, quarter) => size.or(quarter),
+        }
+    }
+}
+
+// === impl Writer ===
+
+impl Writer {
+    /// appends frames to the capture at `path`, creating it if it does not exist.
+    ///
+    /// a capture that already exists with a different header, such as one taken at another tick
+    /// rate, is rotated rather than appended to. a file that is not a capture is an error.
+    ///
+    /// old rotated files are deleted, if they would use more space than `rotation` allows.
+    pub fn create(
+        path: impl Into<PathBuf>,
+        header: Header,
+        rotation: Rotation,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        // a capture that already existed is assumed to hold frames.
+        let existing = Self::header(&path)?;
+        let (file, size) = Self::open(&path, &header)?;
+        let mut writer = Self {
+            path,
+            header,
+            rotation,
+            file,
+            size,
+            frames: existing.is_some(),
+            opened: Instant::now(),
+        };
+
+        match existing {
+            Some(existing) if existing != writer.header => writer.rotate()?,
+            _ => writer.prune()?,
+        }
+        Ok(writer)
+    }
+
+    /// appends a frame, first rotating the file if it is due.
+    pub fn write(&mut self, frame: &Frame) -> io::Result<()> {
+        let frame = frame.encode();
+        if self.due(frame.len() as u64) {
+            self.rotate()?;
+        }
+
+        self.file.write_all(&frame)?;
+        self.size += frame.len() as u64;
+        self.frames = true;
+        Ok(())
+    }
+
+    /// returns the header of the capture at `path`, or `None` if there is no file or it is empty.
+    fn header(path: &Path) -> io::Result<Option<Header>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        if file.metadata()?.len() == 0 {
+            return Ok(None);
+        }
+
+        let reader = Reader::new(io::BufReader::new(file)).map_err(|error| {
+            let path = path.display();
+            io::Error::new(error.kind(), format!("cannot append to {path}: {error}"))
+        })?;
+        Ok(Some(reader.header))
+    }
+
+    /// opens the file at `path` for appending, writing the header if it is empty, and returns
+    /// it with its size.
+    fn open(path: &Path, header: &Header) -> io::Result<(File, u64)> {
+        let mut file = File::options().create(true).append(true).open(path)?;
+        let mut size = file.metadata()?.len();
+        if size == 0 {
+            let header = header.encode();
+            file.write_all(&header)?;
+            size = header.len() as u64;
+        }
+
+        Ok((file, size))
+    }
+
+    /// returns `true` if the file should be rotated before writing `len` more bytes.
+    ///
+    /// a file is never rotated before it holds a frame, however large the frame is.
+    fn due(&self, len: u64) -> bool {
+        let Self {
+            rotation,
+            size,
+            frames,
+            opened,
+            ..
+        } = self;
+
+        let full = rotation.limit().is_some_and(|limit| size + len > limit);
+        let old = rotation
+            .every
+            .is_some_and(|every| opened.elapsed() >= every);
+        *frames && (full || old)
+    }
+
+    /// renames the file with the next numbered suffix, and starts a new one.
+    fn rotate(&mut self) -> io::Result<()> {
+        let next = self.rotated()?.last().map_or(1, |(n, ..)| n + 1);
+        fs::rename(&self.path, numbered(&self.path, next))?;
+
+        (self.file, self.size) = Self::open(&self.path, &self.header)?;
+        self.frames = false;
+        self.opened = Instant::now();
+        self.prune()
+    }
+
+    /// deletes the oldest rotated files, until all files fit within the total size allowed.
+    ///
+    /// room is left for the file being written to grow until it is rotated.
+    fn prune(&self) -> io::Result<()> {
+        let Self { rotation, size, .. } = self;
+        let Some(max_total) = rotation.max_total else {
+            return Ok(());
+        };
+
+        let rotated = self.rotated()?;
+        let reserved = rotation.limit().map_or(*size, |limit| limit.max(*size));
+        let mut total = reserved + rotated.iter().map(|(.., size)| size).sum::<u64>();
+        for (_, path, size) in rotated {
+            if total <= max_total {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size;
+        }
+
+        Ok(())
+    }
+
+    /// returns the number, path, and size of each rotated file, oldest first.
+    fn rotated(&self) -> io::Result<Vec<(u64, PathBuf, u64)>> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let prefix = format!("{name}.");
+
+        let mut rotated = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let n = entry.file_name().to_str().and_then(|file| {
+                let n = file.strip_prefix(&prefix)?;
+                n.parse::<u64>().ok().filter(|_| !n.starts_with(['+', '0']))
+            });
+            if let Some(n) = n {
+                rotated.push((n, entry.path(), entry.metadata()?.len()));
+            }
+        }
+
+        rotated.sort_unstable_by_key(|(n, ..)| *n);
+        Ok(rotated)
+    }
+}
+
+// === impl Reader ===
+
+impl<R: BufRead> Reader<R> {
+    /// reads the header of a capture, returning a reader of its frames.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if line.trim_end() != MAGIC {
+            return Err(invalid("not a version 1 tach capture"));
+        }
+
+        let (mut tick_rate, mut host) = (None, String::new());
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid("the capture's header is not terminated"));
+            }
+            match line.trim_end().split_once(' ') {
+                Some(("tick_rate", hz)) => tick_rate = hz.parse().ok().and_then(TickRate::new),
+                Some(("host", name)) => host = name.to_owned(),
+                // unknown keys are ignored.
+                Some(_) => {}
+                None if line.trim_end().is_empty() => break,
+                None => {}
+            }
+        }
+
+        let tick_rate = tick_rate.ok_or_else(|| invalid("the capture has no tick rate"))?;
+        Ok(Self {
+            reader,
+            header: Header { tick_rate, host },
+        })
+    }
+
+    /// returns the capture's header.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// reads the next frame, or returns `None` at the end of the capture.
+    fn frame(&mut self) -> io::Result<Option<Frame>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let fields = line.trim_end().strip_prefix("frame ").and_then(|fields| {
+            let mut fields = fields.split(' ').map(|field| field.parse::<u64>().ok());
+            match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                (Some(monotonic), Some(wall), Some(len), None) => Some((monotonic?, wall?, len?)),
+                _ => None,
+            }
+        });
+        let Some((monotonic, wall, len)) = fields else {
+            return Err(invalid(format!(
+                "expected a frame, found `{}`",
+                line.trim_end()
+            )));
+        };
+
+        let mut stats = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut stats)?;
+        if stats.len() as u64 != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the capture ends partway through a frame",
+            ));
+        }
+
+        Ok(Some(Frame {
+            monotonic: Duration::from_nanos(monotonic),
+            wall: SystemTime::UNIX_EPOCH + Duration::from_nanos(wall),
+            stats,
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = io::Result<Frame>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.frame().transpose()
+    }
+}
+
+// === impl Stopped ===
+
+impl Clock for Stopped {
+    fn now(&self) -> Instant {
+        self.0
+    }
+}
+
+/// returns `path`, with a numbered suffix.
+fn numbered(path: &Path, n: u64) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{n}"));
+    PathBuf::from(path)
+}
+
+/// returns the time on the system's monotonic clock.
+fn monotonic() -> Duration {
+    // SAFETY: `ts` is plain data, so all zeroes is a valid value. `clock_gettime` only writes to
+    // the `timespec` it is given, and `CLOCK_MONOTONIC` is supported on every linux system.
+    let mut ts = unsafe { std::mem::zeroed::<libc::timespec>() };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+
+    let secs = u64::try_from(ts.tv_sec).unwrap_or_default();
+    let nanos = u32::try_from(ts.tv_nsec).unwrap_or_default();
+    Duration::new(secs, nanos)
+}
+
+/// returns an error describing malformed capture data.
+fn invalid(reason: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.into())
+}
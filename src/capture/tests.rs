@@ -0,0 +1,311 @@
+use {
+    super::*,
+    crate::source::mock::{MockStatClock, MockStatFile},
+};
+
+/// returns a frame of `stats`, read `secs` seconds after the monotonic clock started.
+fn frame(secs: u64, stats: &str) -> Frame {
+    Frame {
+        monotonic: Duration::from_secs(secs),
+        wall: SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_110_400 + secs),
+        stats: stats.as_bytes().to_vec(),
+    }
+}
+
+fn header() -> Header {
+    Header {
+        tick_rate: TickRate::FALLBACK,
+        host: "box".to_owned(),
+    }
+}
+
+/// returns an empty directory for a test to write captures in.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tach-capture-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+/// returns the names of the files in `dir`, in order.
+fn names(dir: &Path) -> Vec<String> {
+    let mut names = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// returns the frames of the capture at `path`.
+fn frames(path: &Path) -> Vec<Frame> {
+    let reader = Reader::new(io::BufReader::new(File::open(path).unwrap())).unwrap();
+    assert_eq!(reader.header(), &header());
+    reader.collect::<io::Result<_>>().unwrap()
+}
+
+mod format_tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        let mut capture = header().encode();
+        capture.extend(frame(2, "cpu 1 2 3 4\n").encode());
+
+        assert_eq!(
+            String::from_utf8(capture).unwrap(),
+            "tach-capture 1\n\
+             tick_rate 100\n\
+             host box\n\
+             \n\
+             frame 2000000000 1704110402000000000 12\n\
+             cpu 1 2 3 4\n"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        // frames may hold any bytes, including lines that look like frames.
+        let written = [
+            frame(1, "cpu 1 2 3 4\n"),
+            frame(2, ""),
+            frame(3, "frame 1 2 3\n\n"),
+            frame(4, "cpu 5 6 7 8"),
+        ];
+        let mut capture = header().encode();
+        for frame in &written {
+            capture.extend(frame.encode());
+        }
+
+        let mut reader = Reader::new(capture.as_slice()).unwrap();
+        assert_eq!(reader.header(), &header());
+        for frame in written {
+            assert_eq!(reader.next().unwrap().unwrap(), frame);
+        }
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let capture = "tach-capture 1\nkernel 6.1\ntick_rate 250\n\n";
+        let reader = Reader::new(capture.as_bytes()).unwrap();
+        assert_eq!(reader.header().tick_rate, TickRate::new(250).unwrap());
+        assert_eq!(reader.header().host, "");
+    }
+
+    #[test]
+    fn invalid() {
+        let header = |capture: &'static str| Reader::new(capture.as_bytes()).err().unwrap().kind();
+        assert_eq!(header(""), io::ErrorKind::InvalidData);
+        assert_eq!(header("tach-capture 2\n"), io::ErrorKind::InvalidData);
+        assert_eq!(
+            header("tach-capture 1\nhost box\n"),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            header("tach-capture 1\nhost box\n\n"),
+            io::ErrorKind::InvalidData
+        );
+
+        let frame = |frame: &str| {
+            let capture = format!("tach-capture 1\ntick_rate 100\n\n{frame}");
+            let mut reader = Reader::new(capture.as_bytes()).unwrap();
+            reader.next().unwrap().err().unwrap().kind()
+        };
+        assert_eq!(frame("cpu 1 2 3 4\n"), io::ErrorKind::InvalidData);
+        assert_eq!(frame("frame 1 2\n"), io::ErrorKind::InvalidData);
+        assert_eq!(frame("frame 1 2 -3\n"), io::ErrorKind::InvalidData);
+        assert_eq!(frame("frame 1 2 12\ncpu 1"), io::ErrorKind::UnexpectedEof);
+    }
+}
+
+mod frame_tests {
+    use super::*;
+
+    const STATS: [&str; 2] = [
+        "cpu 10 0 10 80 0 0 0 0 0 0\ncpu0 10 0 10 80 0 0 0 0 0 0\nctxt 100\nprocs_running 2\n",
+        "cpu 30 0 10 160 0 0 0 0 0 0\ncpu0 30 0 10 160 0 0 0 0 0 0\nctxt 250\nprocs_running 1\n",
+    ];
+
+    /// frames rebuild exactly the snapshots that would have been taken from their statistics,
+    /// the same distance apart.
+    #[test]
+    fn snapshots() {
+        let base = Instant::now();
+        let clock =
+            MockStatClock::new([base + Duration::from_secs(1), base + Duration::from_secs(2)]);
+        let source = MockStatFile::new(STATS);
+
+        for (secs, stats) in (1..).zip(STATS) {
+            let expected = Snapshot::read(&source, &clock).unwrap();
+            assert_eq!(frame(secs, stats).snapshot(base).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn read() {
+        let before = monotonic();
+        let frame = Frame::read(&MockStatFile::new(STATS)).unwrap();
+        assert_eq!(frame.stats, STATS[0].as_bytes());
+        assert!(frame.monotonic >= before && frame.monotonic <= monotonic());
+    }
+}
+
+mod writer_tests {
+    use super::*;
+
+    #[test]
+    fn append() {
+        let dir = scratch("append");
+        let path = dir.join("capture");
+
+        // a capture that already exists is appended to, without another header.
+        for secs in [1, 2] {
+            let mut writer = Writer::create(&path, header(), Rotation::default()).unwrap();
+            writer.write(&frame(secs, "cpu 1 2 3 4\n")).unwrap();
+        }
+
+        assert_eq!(
+            frames(&path),
+            [frame(1, "cpu 1 2 3 4\n"), frame(2, "cpu 1 2 3 4\n")]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// a capture with a different header is rotated, rather than mixing frames from each.
+    #[test]
+    fn append_mismatch() {
+        let dir = scratch("mismatch");
+        let path = dir.join("capture");
+        let other = Header::new(TickRate::new(250).unwrap()).with_host("other");
+
+        let mut writer = Writer::create(&path, other.clone(), Rotation::default()).unwrap();
+        writer.write(&frame(1, "cpu 1 2 3 4\n")).unwrap();
+        drop(writer);
+        let mut writer = Writer::create(&path, header(), Rotation::default()).unwrap();
+        writer.write(&frame(2, "cpu 1 2 3 4\n")).unwrap();
+
+        assert_eq!(names(&dir), ["capture", "capture.1"]);
+        let rotated = Reader::new(io::BufReader::new(
+            File::open(dir.join("capture.1")).unwrap(),
+        ));
+        assert_eq!(rotated.unwrap().header(), &other);
+        assert_eq!(frames(&path), [frame(2, "cpu 1 2 3 4\n")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// a file that is not a capture is left alone.
+    #[test]
+    fn append_invalid() {
+        let dir = scratch("invalid");
+        let path = dir.join("capture");
+        fs::write(&path, "cpu 1 2 3 4\n").unwrap();
+
+        let error = Writer::create(&path, header(), Rotation::default())
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(names(&dir), ["capture"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "cpu 1 2 3 4\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_size() {
+        let dir = scratch("size");
+        let path = dir.join("capture");
+        let stats = "cpu 1 2 3 4\n".repeat(8);
+        let len = (header().encode().len() + frame(1, &stats).encode().len()) as u64;
+
+        // two frames fit in each file.
+        let rotation = Rotation::default().with_size(2 * len);
+        let mut writer = Writer::create(&path, header(), rotation).unwrap();
+        for secs in 1..=5 {
+            writer.write(&frame(secs, &stats)).unwrap();
+        }
+
+        assert_eq!(names(&dir), ["capture", "capture.1", "capture.2"]);
+        assert_eq!(
+            frames(&dir.join("capture.1")),
+            [frame(1, &stats), frame(2, &stats)]
+        );
+        assert_eq!(
+            frames(&dir.join("capture.2")),
+            [frame(3, &stats), frame(4, &stats)]
+        );
+        assert_eq!(frames(&path), [frame(5, &stats)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// a frame that is larger than the limit by itself is written to a file of its own.
+    #[test]
+    fn oversized_frame() {
+        let dir = scratch("oversized");
+        let path = dir.join("capture");
+
+        let rotation = Rotation::default().with_size(16);
+        let mut writer = Writer::create(&path, header(), rotation).unwrap();
+        for secs in 1..=2 {
+            writer.write(&frame(secs, "cpu 1 2 3 4\n")).unwrap();
+        }
+
+        assert_eq!(names(&dir), ["capture", "capture.1"]);
+        assert_eq!(frames(&dir.join("capture.1")), [frame(1, "cpu 1 2 3 4\n")]);
+        assert_eq!(frames(&path), [frame(2, "cpu 1 2 3 4\n")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_every() {
+        let dir = scratch("every");
+        let path = dir.join("capture");
+
+        let rotation = Rotation::default().with_every(Duration::from_millis(50));
+        let mut writer = Writer::create(&path, header(), rotation).unwrap();
+        writer.write(&frame(1, "cpu 1 2 3 4\n")).unwrap();
+        writer.write(&frame(2, "cpu 1 2 3 4\n")).unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+        writer.write(&frame(3, "cpu 1 2 3 4\n")).unwrap();
+
+        assert_eq!(names(&dir), ["capture", "capture.1"]);
+        assert_eq!(frames(&dir.join("capture.1")).len(), 2);
+        assert_eq!(frames(&path), [frame(3, "cpu 1 2 3 4\n")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn max_total() {
+        let dir = scratch("total");
+        let path = dir.join("capture");
+        let stats = "cpu 1 2 3 4\n".repeat(8);
+        // later frames have longer timestamps.
+        let len = (header().encode().len() + frame(10, &stats).encode().len()) as u64;
+
+        // each file holds a single frame, and four files fit within the total.
+        let rotation = Rotation::default().with_max_total(4 * len);
+        let mut writer = Writer::create(&path, header(), rotation).unwrap();
+        for secs in 1..=10 {
+            writer.write(&frame(secs, &stats)).unwrap();
+        }
+
+        assert_eq!(
+            names(&dir),
+            ["capture", "capture.7", "capture.8", "capture.9"]
+        );
+        assert_eq!(frames(&dir.join("capture.7")), [frame(7, &stats)]);
+        let total = names(&dir)
+            .iter()
+            .map(|name| fs::metadata(dir.join(name)).unwrap().len())
+            .sum::<u64>();
+        assert!(total <= 4 * len);
+
+        // rotation continues from the newest file, after a restart with a smaller bound.
+        drop(writer);
+        let rotation = Rotation::default().with_max_total(2 * len);
+        let mut writer = Writer::create(&path, header(), rotation).unwrap();
+        assert_eq!(names(&dir), ["capture", "capture.9"]);
+        writer.write(&frame(11, &stats)).unwrap();
+        assert_eq!(names(&dir), ["capture", "capture.10"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
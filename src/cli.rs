@@ -0,0 +1,742 @@
+pub use crate::{meter::Align, output::BarScope};
+
+use {
+    crate::{
+        capture::Rotation,
+        stat::{Accounting, CpuId, TickRate},
+    },
+    std::{
+        fmt::{self, Display},
+        net::SocketAddr,
+        ops::RangeInclusive,
+        path::PathBuf,
+        time::Duration,
+    },
+};
+
+#[cfg(test)]
+mod tests;
+
+/// a command, parsed from the command line.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// run tach with the given options.
+    Run(Box<Options>),
+    /// print usage information.
+    Help,
+    /// print the version.
+    Version,
+}
+
+/// options for running tach.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// how long to wait between samples.
+    pub interval: Duration,
+    /// the number of samples to take before exiting, or `None` to run until interrupted.
+    pub count: Option<u64>,
+    /// the cpus to show, or `None` to show every cpu.
+    pub cpus: Option<CpuSelection>,
+    /// how samples are shown.
+    pub output: Output,
+    /// where line-oriented output is written.
+    pub to: Destination,
+    /// how csv output is laid out.
+    pub csv_layout: CsvLayout,
+    /// the address to serve metrics on.
+    pub listen: SocketAddr,
+    /// prepended to the name of each statsd gauge.
+    pub statsd_prefix: String,
+    /// tags sent with each dogstatsd gauge.
+    pub statsd_tags: Vec<String>,
+    /// how otlp requests are encoded.
+    pub otlp_encoding: OtlpEncoding,
+    /// overrides the system's clock tick rate.
+    pub tick_rate: Option<TickRate>,
+    /// decides which cpu states count as busy.
+    pub accounting: Accounting,
+    /// the width of each cpu's meter in the tui, in cells.
+    pub meter_width: usize,
+    /// where the filled part of each cpu's meter is placed.
+    pub meter_align: Align,
+    /// what status bars show.
+    pub bar_scope: BarScope,
+    /// a capture file to record raw statistics to, rather than showing samples.
+    pub record: Option<PathBuf>,
+    /// when the capture file is rotated.
+    pub rotation: Rotation,
+}
+
+/// how samples are shown.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Output {
+    /// the tui if stdout is a terminal, and text otherwise.
+    #[default]
+    Auto,
+    /// a full-screen terminal interface.
+    Tui,
+    /// rows of plain text per sample, like `mpstat -P ALL`.
+    Text,
+    /// a json object per sample, on its own line.
+    Json,
+    /// comma-separated values, with a header row.
+    Csv,
+    /// metrics served over http, in the prometheus text format.
+    Prometheus,
+    /// lines in the influxdb line protocol.
+    Influx,
+    /// gauges sent to a statsd agent over udp.
+    Statsd,
+    /// gauges sent to a dogstatsd agent over udp, with tags.
+    Dogstatsd,
+    /// metrics pushed to an opentelemetry collector over http.
+    Otlp,
+    /// a status line for tmux, with format escapes.
+    Tmux,
+    /// blocks for i3bar or swaybar, in the i3bar protocol.
+    I3bar,
+    /// json for a waybar custom module.
+    Waybar,
+}
+
+/// how otlp requests are encoded.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OtlpEncoding {
+    /// binary protobuf.
+    #[default]
+    Protobuf,
+    /// the json mapping of protobuf.
+    Json,
+}
+
+/// where line-oriented output is written.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum Destination {
+    /// standard output.
+    #[default]
+    Stdout,
+    /// a file, which is appended to.
+    File(PathBuf),
+    /// datagrams, sent to a udp port.
+    Udp {
+        /// the host to send to.
+        host: String,
+        /// the port to send to.
+        port: u16,
+    },
+    /// http `POST` requests.
+    Http {
+        /// the host to send to.
+        host: String,
+        /// the port to send to.
+        port: u16,
+        /// the path and query string to request.
+        path: String,
+    },
+}
+
+/// how csv output is laid out.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CsvLayout {
+    /// a row for each cpu in each sample.
+    #[default]
+    Long,
+    /// a row for each sample, with a column for each state of each cpu.
+    Wide,
+}
+
+/// a selection of cpus, such as `0,2,4-7`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CpuSelection {
+    ranges: Vec<RangeInclusive<u32>>,
+}
+
+/// an error parsing the command line.
+#[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum CliError {
+    /// an argument was not recognized.
+    UnknownArgument(String),
+    /// an option was given without a value.
+    MissingValue {
+        /// the option.
+        option: &'static str,
+    },
+    /// an option that does not take a value was given one.
+    UnexpectedValue {
+        /// the option.
+        option: String,
+    },
+    /// an option was given an invalid value.
+    InvalidValue {
+        /// the option.
+        option: &'static str,
+        /// the value that was given.
+        value: String,
+        /// why the value is invalid.
+        reason: &'static str,
+    },
+    /// two options that cannot be used together were given.
+    Conflict {
+        /// the option.
+        option: &'static str,
+        /// the option that it cannot be used with.
+        with: &'static str,
+    },
+}
+
+/// usage information, printed by `--help`.
+pub const USAGE: &str = "\
+a compact cpu monitor
+
+usage: tach [options]
+
+options:
+  -i, --interval <time>    time between samples, in seconds or milliseconds [default: 1s]
+                           (e.g. `2`, `0.5`, `250ms`)
+  -n, --count <n>          exit after taking <n> samples
+      --once               take a single sample, and exit (the same as `--count 1`)
+  -c, --cpus <list>        only show the given cpus (e.g. `0,2,4-7`) [default: all]
+  -o, --output <mode>      how to show samples: `auto`, `tui`, `text`, `json`, `csv`,
+                           `prometheus`, `influx`, `statsd`, `dogstatsd`, `otlp`, or a status
+                           bar: `tmux`, `i3bar` (also for swaybar), or `waybar` [default: auto]
+                           (`auto` uses the tui on a terminal, and text otherwise)
+      --to <dest>          write text, json, csv, or influx output to <dest>: `-` for stdout,
+                           a file, `udp://host:port`, or `http://host:port/path` [default: -]
+                           (statsd is sent to `udp://host:port` [default: udp://127.0.0.1:8125],
+                           and otlp to `http://host:port/path`
+                           [default: http://127.0.0.1:4318/v1/metrics])
+      --statsd-prefix <p>  prepend <p> to the name of each statsd gauge [default: tach]
+      --statsd-tags <t>    tag each dogstatsd gauge with <t> (e.g. `env:prod,team:infra`)
+      --otlp-encoding <e>  encode otlp requests as `protobuf` or `json` [default: protobuf]
+      --csv-layout <l>     write a csv row per cpu (`long`), or per sample (`wide`) [default: long]
+      --listen <addr>      serve prometheus metrics on <addr> [default: 127.0.0.1:9789]
+      --clock-ticks <hz>   interpret times using <hz> clock ticks per second
+                           [default: queried from the system]
+      --iowait <mode>      count time waiting for i/o as `busy` or `idle` [default: idle]
+      --steal <mode>       count time stolen by a hypervisor as `busy` or `idle` [default: busy]
+      --meter-width <n>    the width of each cpu's meter, in cells [default: 8]
+      --meter-align <a>    fill meters from the `left`, `right`, or `center` [default: left]
+      --bar-scope <s>      show a meter for the whole `system` in status bars, or a column of
+                           dots for each of the `cpus` [default: system]
+      --record <file>      append each raw read of `/proc/stat` to the capture <file>, rather
+                           than showing samples (`--count` is then the number of reads, and
+                           `--output` and `--to` cannot be given)
+      --rotate-size <n>    rotate the capture file before it grows beyond <n> bytes
+                           (e.g. `512K`, `64M`, `1G`)
+      --rotate-every <t>   rotate the capture file after <t> (e.g. `30m`, `1h`)
+      --max-total <n>      delete the oldest rotated capture files, to keep all of them within
+                           <n> bytes (files are then rotated at a quarter of <n>, at most)
+  -h, --help               print this help, and exit
+  -V, --version            print the version, and exit
+
+keys:
+  v, tab                   switch between sparklines and per-state bars
+  up, down, k, j           scroll the cpus, when they do not fit
+  pgup, pgdn, home, end    scroll the cpus a page, or to the first or last
+  q, esc, ctrl-c           exit
+";
+
+// === impl Command ===
+
+impl Command {
+    /// parses a command from the given arguments, excluding the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        // the last option given that chooses how samples are shown.
+        let mut shown = None;
+
+        while let Some(arg) = args.next() {
+            // long options may be given their value inline, as in `--interval=2`.
+            let (flag, mut inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_owned(), Some(value.to_owned()))
+                }
+                _ => (arg, None),
+            };
+            let has_value = inline.is_some();
+            let mut value = |option: &'static str| {
+                inline
+                    .take()
+                    .or_else(|| args.next())
+                    .ok_or(CliError::MissingValue { option })
+            };
+
+            match flag.as_str() {
+                "--help" | "--version" | "--once" if has_value => {
+                    return Err(CliError::UnexpectedValue { option: flag });
+                }
+                "-h" | "--help" => return Ok(Self::Help),
+                "-V" | "--version" => return Ok(Self::Version),
+                "-i" | "--interval" => {
+                    options.interval = parse_duration("--interval", value("--interval")?)?
+                }
+                "-n" | "--count" => options.count = Some(parse_count(value("--count")?)?),
+                "--once" => options.count = Some(1),
+                "-c" | "--cpus" => options.cpus = parse_cpus(value("--cpus")?)?,
+                "-o" | "--output" => {
+                    options.output = value("--output")?.parse()?;
+                    shown = Some("--output");
+                }
+                "--to" => {
+                    options.to = value("--to")?.parse()?;
+                    shown = Some("--to");
+                }
+                "--csv-layout" => options.csv_layout = value("--csv-layout")?.parse()?,
+                "--listen" => options.listen = parse_listen(value("--listen")?)?,
+                "--statsd-prefix" => {
+                    options.statsd_prefix = parse_statsd_prefix(value("--statsd-prefix")?)?
+                }
+                "--statsd-tags" => {
+                    options.statsd_tags = parse_statsd_tags(value("--statsd-tags")?)?
+                }
+                "--otlp-encoding" => options.otlp_encoding = value("--otlp-encoding")?.parse()?,
+                "--clock-ticks" => {
+                    options.tick_rate = Some(parse_tick_rate(value("--clock-ticks")?)?)
+                }
+                "--iowait" => {
+                    let busy = parse_busy("--iowait", value("--iowait")?)?;
+                    options.accounting = options.accounting.with_iowait_busy(busy);
+                }
+                "--steal" => {
+                    let busy = parse_busy("--steal", value("--steal")?)?;
+                    options.accounting = options.accounting.with_steal_busy(busy);
+                }
+                "--meter-width" => {
+                    options.meter_width = parse_meter_width(value("--meter-width")?)?
+                }
+                "--meter-align" => options.meter_align = value("--meter-align")?.parse()?,
+                "--bar-scope" => options.bar_scope = value("--bar-scope")?.parse()?,
+                "--record" => options.record = Some(PathBuf::from(value("--record")?)),
+                "--rotate-size" => {
+                    let size = parse_size("--rotate-size", value("--rotate-size")?)?;
+                    options.rotation = options.rotation.with_size(size);
+                }
+                "--rotate-every" => {
+                    let every = parse_duration("--rotate-every", value("--rotate-every")?)?;
+                    options.rotation = options.rotation.with_every(every);
+                }
+                "--max-total" => {
+                    let size = parse_size("--max-total", value("--max-total")?)?;
+                    options.rotation = options.rotation.with_max_total(size);
+                }
+                _ => return Err(CliError::UnknownArgument(flag)),
+            }
+        }
+
+        // samples are not shown while recording.
+        if let (Some(_), Some(with)) = (&options.record, shown) {
+            return Err(CliError::Conflict {
+                option: "--record",
+                with,
+            });
+        }
+
+        Ok(Self::Run(Box::new(options)))
+    }
+}
+
+/// parses a time in seconds, or with a unit of `ms`, `s`, `m`, or `h`.
+fn parse_duration(option: &'static str, value: String) -> Result<Duration, CliError> {
+    let invalid = |reason| CliError::InvalidValue {
+        option,
+        value: value.clone(),
+        reason,
+    };
+
+    let (number, scale) = [("ms", 1e-3), ("s", 1.0), ("m", 60.0), ("h", 3600.0)]
+        .into_iter()
+        .find_map(|(unit, scale)| Some((value.strip_suffix(unit)?, scale)))
+        .unwrap_or((&value, 1.0));
+    let secs = number
+        .parse::<f64>()
+        .map_err(|_| invalid("expected a time, like `2`, `0.5`, `250ms`, or `1h`"))?;
+
+    Duration::try_from_secs_f64(secs * scale)
+        .ok()
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(|| invalid("the time must be greater than zero"))
+}
+
+/// parses a number of bytes, with an optional binary suffix of `K`, `M`, or `G`.
+fn parse_size(option: &'static str, value: String) -> Result<u64, CliError> {
+    let invalid = |reason| CliError::InvalidValue {
+        option,
+        value: value.clone(),
+        reason,
+    };
+
+    let number = value
+        .strip_suffix("iB")
+        .or_else(|| value.strip_suffix('B'))
+        .unwrap_or(&value);
+    let (number, scale) = [('K', 1 << 10), ('M', 1 << 20), ('G', 1 << 30)]
+        .into_iter()
+        .find_map(|(unit, scale)| Some((number.strip_suffix(unit)?, scale)))
+        .unwrap_or((number, 1));
+    let bytes = number
+        .parse::<u64>()
+        .map_err(|_| invalid("expected a size, like `4096`, `512K`, or `64M`"))?;
+
+    match bytes.checked_mul(scale) {
+        Some(0) => Err(invalid("the size must be greater than zero")),
+        Some(bytes) => Ok(bytes),
+        None => Err(invalid("the size is too large")),
+    }
+}
+
+fn parse_listen(value: String) -> Result<SocketAddr, CliError> {
+    value.parse().map_err(|_| CliError::InvalidValue {
+        option: "--listen",
+        value,
+        reason: "expected an address and port, like `127.0.0.1:9789`",
+    })
+}
+
+/// parses a statsd prefix, which may not contain the characters that delimit a gauge.
+fn parse_statsd_prefix(value: String) -> Result<String, CliError> {
+    match value.contains([':', '|', '@', '#', '\n']) || value.contains(char::is_whitespace) {
+        false => Ok(value.trim_end_matches('.').to_owned()),
+        true => Err(CliError::InvalidValue {
+            option: "--statsd-prefix",
+            value,
+            reason: "expected a name without spaces, `:`, `|`, `@`, or `#`",
+        }),
+    }
+}
+
+/// parses a comma-separated list of dogstatsd tags.
+fn parse_statsd_tags(value: String) -> Result<Vec<String>, CliError> {
+    let tags = value
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty());
+    let valid = |tag: &&str| !tag.contains(['|', '#']) && !tag.contains(char::is_whitespace);
+    match tags.clone().all(|tag| valid(&tag)) {
+        true => Ok(tags.map(str::to_owned).collect()),
+        false => Err(CliError::InvalidValue {
+            option: "--statsd-tags",
+            value,
+            reason: "expected tags without spaces, `|`, or `#`, like `env:prod,team:infra`",
+        }),
+    }
+}
+
+fn parse_count(value: String) -> Result<u64, CliError> {
+    match value.parse::<u64>() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(CliError::InvalidValue {
+            option: "--count",
+            value,
+            reason: "expected a positive integer",
+        }),
+    }
+}
+
+fn parse_tick_rate(value: String) -> Result<TickRate, CliError> {
+    value
+        .parse::<u64>()
+        .ok()
+        .and_then(TickRate::new)
+        .ok_or(CliError::InvalidValue {
+            option: "--clock-ticks",
+            value,
+            reason: "expected a positive integer",
+        })
+}
+
+/// parses whether a cpu state counts as `busy` or `idle`.
+fn parse_busy(option: &'static str, value: String) -> Result<bool, CliError> {
+    match value.as_str() {
+        "busy" => Ok(true),
+        "idle" => Ok(false),
+        _ => Err(CliError::InvalidValue {
+            option,
+            value,
+            reason: "expected `busy` or `idle`",
+        }),
+    }
+}
+
+fn parse_meter_width(value: String) -> Result<usize, CliError> {
+    /// the widest meter that can be drawn.
+    const MAX: usize = 256;
+
+    match value.parse::<usize>() {
+        Ok(width) if (1..=MAX).contains(&width) => Ok(width),
+        _ => Err(CliError::InvalidValue {
+            option: "--meter-width",
+            value,
+            reason: "expected an integer from 1 to 256",
+        }),
+    }
+}
+
+/// parses a comma-separated list of cpu ids and ranges, or `all`.
+fn parse_cpus(value: String) -> Result<Option<CpuSelection>, CliError> {
+    match value.as_str() {
+        "all" => Ok(None),
+        _ => value.parse().map(Some),
+    }
+}
+
+// === impl Options ===
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            count: None,
+            cpus: None,
+            output: Output::default(),
+            to: Destination::default(),
+            csv_layout: CsvLayout::default(),
+            listen: SocketAddr::from(([127, 0, 0, 1], 9789)),
+            statsd_prefix: "tach".to_owned(),
+            statsd_tags: Vec::new(),
+            otlp_encoding: OtlpEncoding::default(),
+            tick_rate: None,
+            accounting: Accounting::default(),
+            meter_width: 8,
+            meter_align: Align::default(),
+            bar_scope: BarScope::default(),
+            record: None,
+            rotation: Rotation::default(),
+        }
+    }
+}
+
+// === impl Output ===
+
+impl std::str::FromStr for Output {
+    type Err = CliError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "tui" => Ok(Self::Tui),
+            "text" => Ok(Self::Text),
+            "json" | "jsonl" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "prometheus" => Ok(Self::Prometheus),
+            "influx" | "influxdb" => Ok(Self::Influx),
+            "statsd" => Ok(Self::Statsd),
+            "dogstatsd" => Ok(Self::Dogstatsd),
+            "otlp" => Ok(Self::Otlp),
+            "tmux" => Ok(Self::Tmux),
+            "i3bar" | "swaybar" => Ok(Self::I3bar),
+            "waybar" => Ok(Self::Waybar),
+            other => Err(CliError::InvalidValue {
+                option: "--output",
+                value: other.to_owned(),
+                reason: "expected `auto`, `tui`, `text`, `json`, `csv`, `prometheus`, `influx`, \
+                         `statsd`, `dogstatsd`, `otlp`, `tmux`, `i3bar`, or `waybar`",
+            }),
+        }
+    }
+}
+
+// === impl Destination ===
+
+/// parses a destination: `-`, `udp://host:port`, `http://host:port/path`, or a file path.
+///
+/// files may also be given as `file:path`, for paths that look like urls.
+impl std::str::FromStr for Destination {
+    type Err = CliError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| CliError::InvalidValue {
+            option: "--to",
+            value: s.to_owned(),
+            reason,
+        };
+
+        // splits `host:port`, requiring a port unless a default is given.
+        let authority = |authority: &str, default: Option<u16>| {
+            let split = authority
+                .rsplit_once(':')
+                .filter(|_| !authority.ends_with(']'));
+            let (host, port) = match split {
+                Some((host, port)) => (host, port.parse().ok()),
+                None => (authority, default),
+            };
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            match (host, port) {
+                ("", _) | (_, None) => {
+                    Err(invalid("expected a host and port, like `localhost:8094`"))
+                }
+                (host, Some(port)) => Ok((host.to_owned(), port)),
+            }
+        };
+
+        if s == "-" {
+            Ok(Self::Stdout)
+        } else if let Some(path) = s.strip_prefix("file:") {
+            Ok(Self::File(path.into()))
+        } else if let Some(rest) = s.strip_prefix("udp://") {
+            let (host, port) = authority(rest.trim_end_matches('/'), None)?;
+            Ok(Self::Udp { host, port })
+        } else if let Some(rest) = s.strip_prefix("http://") {
+            let (rest, path) = match rest.find(['/', '?']) {
+                Some(i) if rest[i..].starts_with('?') => (&rest[..i], format!("/{}", &rest[i..])),
+                Some(i) => (&rest[..i], rest[i..].to_owned()),
+                None => (rest, "/".to_owned()),
+            };
+            let (host, port) = authority(rest, Some(80))?;
+            Ok(Self::Http { host, port, path })
+        } else if s.contains("://") {
+            Err(invalid(
+                "expected `-`, a file, `udp://host:port`, or `http://host:port/path`",
+            ))
+        } else {
+            Ok(Self::File(s.into()))
+        }
+    }
+}
+
+// === impl CsvLayout ===
+
+impl std::str::FromStr for CsvLayout {
+    type Err = CliError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "long" => Ok(Self::Long),
+            "wide" => Ok(Self::Wide),
+            other => Err(CliError::InvalidValue {
+                option: "--csv-layout",
+                value: other.to_owned(),
+                reason: "expected `long` or `wide`",
+            }),
+        }
+    }
+}
+
+// === impl OtlpEncoding ===
+
+impl std::str::FromStr for OtlpEncoding {
+    type Err = CliError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "protobuf" | "proto" => Ok(Self::Protobuf),
+            "json" => Ok(Self::Json),
+            other => Err(CliError::InvalidValue {
+                option: "--otlp-encoding",
+                value: other.to_owned(),
+                reason: "expected `protobuf` or `json`",
+            }),
+        }
+    }
+}
+
+// === impl Align ===
+
+impl std::str::FromStr for Align {
+    type Err = CliError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "center" | "centre" => Ok(Self::Center),
+            other => Err(CliError::InvalidValue {
+                option: "--meter-align",
+                value: other.to_owned(),
+                reason: "expected `left`, `right`, or `center`",
+            }),
+        }
+    }
+}
+
+// === impl BarScope ===
+
+impl std::str::FromStr for BarScope {
+    type Err = CliError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "system" | "all" => Ok(Self::System),
+            "cpus" => Ok(Self::Cpus),
+            other => Err(CliError::InvalidValue {
+                option: "--bar-scope",
+                value: other.to_owned(),
+                reason: "expected `system` or `cpus`",
+            }),
+        }
+    }
+}
+
+// === impl CpuSelection ===
+
+impl CpuSelection {
+    /// returns `true` if the given cpu is selected.
+    pub fn contains(&self, id: &CpuId) -> bool {
+        self.ranges.iter().any(|range| range.contains(&id.get()))
+    }
+
+    /// returns each selected cpu, in the order they were listed.
+    pub fn ids(&self) -> impl Iterator<Item = CpuId> + '_ {
+        self.ranges.iter().cloned().flatten().map(CpuId::from)
+    }
+}
+
+/// parses a comma-separated list of cpu ids and ranges, such as `0,2,4-7`.
+///
+/// this is also the format of the kernel's cpu lists, such as `/sys/devices/system/cpu/possible`.
+impl std::str::FromStr for CpuSelection {
+    type Err = CliError;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| CliError::InvalidValue {
+            option: "--cpus",
+            value: value.to_owned(),
+            reason,
+        };
+
+        let id = |id: &str| {
+            let id = id.trim();
+            id.strip_prefix("cpu")
+                .unwrap_or(id)
+                .parse::<u32>()
+                .map_err(|_| invalid("expected a list of cpus, like `0,2,4-7`"))
+        };
+
+        let ranges = value
+            .trim_end()
+            .split(',')
+            .map(|part| {
+                let (first, last) = part.split_once('-').unwrap_or((part, part));
+                let (first, last) = (id(first)?, id(last)?);
+                match first <= last {
+                    true => Ok(first..=last),
+                    false => Err(invalid("ranges must be in ascending order")),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { ranges })
+    }
+}
+
+// === impl CliError ===
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownArgument(arg) => f.write_fmt(format_args!("unknown argument: {arg}")),
+            Self::MissingValue { option } => {
+                f.write_fmt(format_args!("missing a value for {option}"))
+            }
+            Self::UnexpectedValue { option } => {
+                f.write_fmt(format_args!("{option} does not take a value"))
+            }
+            Self::InvalidValue {
+                option,
+                value,
+                reason,
+            } => f.write_fmt(format_args!(
+                "invalid value `{value}` for {option}: {reason}"
+            )),
+            Self::Conflict { option, with } => {
+                f.write_fmt(format_args!("{option} cannot be used with {with}"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
@@ -0,0 +1,434 @@
+use super::*;
+
+fn parse(args: &[&str]) -> Result<Command, CliError> {
+    Command::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+fn options(args: &[&str]) -> Options {
+    match parse(args) {
+        Ok(Command::Run(options)) => *options,
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+mod command_tests {
+    use super::*;
+
+    #[test]
+    fn no_arguments() {
+        assert_eq!(options(&[]), Options::default());
+    }
+
+    #[test]
+    fn help() {
+        assert_eq!(parse(&["--help"]), Ok(Command::Help));
+        assert_eq!(parse(&["-n", "3", "-h"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn version() {
+        assert_eq!(parse(&["-V"]), Ok(Command::Version));
+        assert_eq!(parse(&["--version"]), Ok(Command::Version));
+    }
+
+    #[test]
+    fn unknown() {
+        assert_eq!(
+            parse(&["--frobnicate"]),
+            Err(CliError::UnknownArgument("--frobnicate".to_owned()))
+        );
+    }
+
+    #[test]
+    fn missing_value() {
+        assert_eq!(
+            parse(&["--count"]),
+            Err(CliError::MissingValue { option: "--count" })
+        );
+    }
+
+    #[test]
+    fn unexpected_value() {
+        assert_eq!(
+            parse(&["--help=yes"]),
+            Err(CliError::UnexpectedValue {
+                option: "--help".to_owned()
+            })
+        );
+        assert!(matches!(
+            parse(&["--count=3=4"]),
+            Err(CliError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn inline_value() {
+        assert_eq!(options(&["--count=3"]).count, Some(3));
+    }
+
+    #[test]
+    fn output() {
+        assert_eq!(options(&["-o", "text"]).output, Output::Text);
+        assert_eq!(options(&["--output", "json"]).output, Output::Json);
+        assert_eq!(options(&["--output=tui"]).output, Output::Tui);
+        assert_eq!(options(&["-o", "auto"]).output, Output::Auto);
+        assert_eq!(options(&[]).output, Output::Auto);
+        assert_eq!(options(&["-o", "csv"]).output, Output::Csv);
+        assert_eq!(options(&["-o", "prometheus"]).output, Output::Prometheus);
+        assert_eq!(options(&["-o", "influx"]).output, Output::Influx);
+        assert_eq!(options(&["-o", "statsd"]).output, Output::Statsd);
+        assert_eq!(options(&["-o", "dogstatsd"]).output, Output::Dogstatsd);
+        assert_eq!(options(&["-o", "otlp"]).output, Output::Otlp);
+        assert_eq!(options(&["-o", "tmux"]).output, Output::Tmux);
+        assert_eq!(options(&["-o", "swaybar"]).output, Output::I3bar);
+        assert_eq!(options(&["-o", "waybar"]).output, Output::Waybar);
+        assert!(matches!(
+            parse(&["-o", "xml"]),
+            Err(CliError::InvalidValue {
+                option: "--output",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn count() {
+        assert_eq!(options(&["-n", "10"]).count, Some(10));
+        assert!(parse(&["-n", "0"]).is_err());
+        assert!(parse(&["-n", "-1"]).is_err());
+    }
+
+    #[test]
+    fn once() {
+        assert_eq!(options(&["--once"]).count, Some(1));
+        assert_eq!(options(&["-n", "5", "--once"]).count, Some(1));
+        assert!(matches!(
+            parse(&["--once=2"]),
+            Err(CliError::UnexpectedValue { .. })
+        ));
+    }
+
+    #[test]
+    fn bar_scope() {
+        assert_eq!(options(&[]).bar_scope, BarScope::System);
+        assert_eq!(options(&["--bar-scope", "cpus"]).bar_scope, BarScope::Cpus);
+        assert!(parse(&["--bar-scope", "cores"]).is_err());
+    }
+
+    #[test]
+    fn record() {
+        let options = options(&[
+            "--record",
+            "/tmp/tach.capture",
+            "--rotate-size",
+            "64M",
+            "--rotate-every=1h",
+            "--max-total",
+            "1G",
+        ]);
+        assert_eq!(options.record, Some(PathBuf::from("/tmp/tach.capture")));
+        assert_eq!(
+            options.rotation,
+            Rotation::default()
+                .with_size(64 << 20)
+                .with_every(Duration::from_secs(3600))
+                .with_max_total(1 << 30)
+        );
+        assert!(parse(&["--rotate-size", "0"]).is_err());
+        assert!(parse(&["--rotate-every", "0m"]).is_err());
+    }
+
+    /// samples are not shown while recording, so options for showing them are refused.
+    #[test]
+    fn record_conflicts() {
+        assert_eq!(
+            parse(&["--record", "capture", "--output", "json"]),
+            Err(CliError::Conflict {
+                option: "--record",
+                with: "--output",
+            })
+        );
+        assert_eq!(
+            parse(&["--to=out.csv", "--record=capture"]),
+            Err(CliError::Conflict {
+                option: "--record",
+                with: "--to",
+            })
+        );
+    }
+
+    #[test]
+    fn clock_ticks() {
+        assert_eq!(
+            options(&["--clock-ticks", "250"]).tick_rate,
+            TickRate::new(250)
+        );
+        assert!(parse(&["--clock-ticks", "0"]).is_err());
+    }
+
+    #[test]
+    fn listen() {
+        let listen = options(&["--listen", "0.0.0.0:9100"]).listen;
+        assert_eq!(listen, SocketAddr::from(([0, 0, 0, 0], 9100)));
+        assert!(parse(&["--listen", "localhost"]).is_err());
+    }
+
+    #[test]
+    fn statsd() {
+        assert_eq!(options(&[]).statsd_prefix, "tach");
+        assert_eq!(
+            options(&["--statsd-prefix", "web.tach."]).statsd_prefix,
+            "web.tach"
+        );
+        assert_eq!(options(&["--statsd-prefix="]).statsd_prefix, "");
+        assert!(parse(&["--statsd-prefix", "a|b"]).is_err());
+
+        assert_eq!(options(&[]).statsd_tags, Vec::<String>::new());
+        assert_eq!(
+            options(&["--statsd-tags", "env:prod, canary,"]).statsd_tags,
+            ["env:prod", "canary"]
+        );
+        assert!(parse(&["--statsd-tags", "env:prod|g"]).is_err());
+        assert!(parse(&["--statsd-tags", "env:my prod"]).is_err());
+    }
+
+    #[test]
+    fn otlp_encoding() {
+        assert_eq!(options(&[]).otlp_encoding, OtlpEncoding::Protobuf);
+        assert_eq!(
+            options(&["--otlp-encoding", "json"]).otlp_encoding,
+            OtlpEncoding::Json
+        );
+        assert!(parse(&["--otlp-encoding", "grpc"]).is_err());
+    }
+
+    #[test]
+    fn csv_layout() {
+        assert_eq!(options(&[]).csv_layout, CsvLayout::Long);
+        assert_eq!(
+            options(&["--csv-layout", "wide"]).csv_layout,
+            CsvLayout::Wide
+        );
+        assert!(parse(&["--csv-layout", "tall"]).is_err());
+    }
+
+    #[test]
+    fn accounting() {
+        let accounting = options(&["--iowait", "busy", "--steal=idle"]).accounting;
+        assert!(accounting.iowait_busy());
+        assert!(!accounting.steal_busy());
+        assert_eq!(super::options(&[]).accounting, Accounting::default());
+        assert!(parse(&["--iowait", "sometimes"]).is_err());
+    }
+
+    #[test]
+    fn meter() {
+        let options = options(&["--meter-width", "12", "--meter-align=center"]);
+        assert_eq!(options.meter_width, 12);
+        assert_eq!(options.meter_align, Align::Center);
+        assert_eq!(super::options(&[]).meter_align, Align::Left);
+        assert!(parse(&["--meter-width", "0"]).is_err());
+        assert!(parse(&["--meter-width", "1000"]).is_err());
+        assert!(parse(&["--meter-align", "up"]).is_err());
+    }
+}
+
+mod destination_tests {
+    use super::*;
+
+    fn to(value: &str) -> Result<Destination, CliError> {
+        value.parse()
+    }
+
+    #[test]
+    fn stdout() {
+        assert_eq!(options(&[]).to, Destination::Stdout);
+        assert_eq!(to("-"), Ok(Destination::Stdout));
+    }
+
+    #[test]
+    fn file() {
+        assert_eq!(to("out.lp"), Ok(Destination::File("out.lp".into())));
+        assert_eq!(to("/tmp/out"), Ok(Destination::File("/tmp/out".into())));
+        assert_eq!(to("file:udp://x"), Ok(Destination::File("udp://x".into())));
+    }
+
+    #[test]
+    fn udp() {
+        assert_eq!(
+            to("udp://localhost:8094"),
+            Ok(Destination::Udp {
+                host: "localhost".to_owned(),
+                port: 8094
+            })
+        );
+        assert_eq!(
+            to("udp://[::1]:8094"),
+            Ok(Destination::Udp {
+                host: "::1".to_owned(),
+                port: 8094
+            })
+        );
+        assert!(to("udp://localhost").is_err());
+        assert!(to("udp://:8094").is_err());
+    }
+
+    #[test]
+    fn http() {
+        let http = |host: &str, port, path: &str| {
+            Ok(Destination::Http {
+                host: host.to_owned(),
+                port,
+                path: path.to_owned(),
+            })
+        };
+        assert_eq!(
+            to("http://127.0.0.1:8086/write?db=tach"),
+            http("127.0.0.1", 8086, "/write?db=tach")
+        );
+        assert_eq!(to("http://localhost"), http("localhost", 80, "/"));
+        assert_eq!(to("http://localhost?x=1"), http("localhost", 80, "/?x=1"));
+        assert_eq!(to("http://[::1]/write"), http("::1", 80, "/write"));
+        assert!(to("http://localhost:port/").is_err());
+        assert!(to("https://localhost/").is_err());
+    }
+
+    #[test]
+    fn option() {
+        assert_eq!(
+            options(&["--to", "udp://localhost:8094"]).to,
+            Destination::Udp {
+                host: "localhost".to_owned(),
+                port: 8094
+            }
+        );
+        assert!(matches!(
+            parse(&["--to=tcp://localhost:1"]),
+            Err(CliError::InvalidValue { option: "--to", .. })
+        ));
+    }
+}
+
+mod interval_tests {
+    use super::*;
+
+    fn interval(value: &str) -> Result<Duration, CliError> {
+        parse_duration("--interval", value.to_owned())
+    }
+
+    #[test]
+    fn seconds() {
+        assert_eq!(interval("2"), Ok(Duration::from_secs(2)));
+        assert_eq!(interval("2s"), Ok(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn sub_second() {
+        assert_eq!(interval("0.5"), Ok(Duration::from_millis(500)));
+        assert_eq!(interval("250ms"), Ok(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn minutes_and_hours() {
+        assert_eq!(interval("1.5m"), Ok(Duration::from_secs(90)));
+        assert_eq!(interval("2h"), Ok(Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn zero() {
+        assert!(interval("0").is_err());
+        assert!(interval("0ms").is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(interval("soon").is_err());
+        assert!(interval("-1").is_err());
+        assert!(interval("inf").is_err());
+    }
+}
+
+mod size_tests {
+    use super::*;
+
+    fn size(value: &str) -> Result<u64, CliError> {
+        parse_size("--rotate-size", value.to_owned())
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(size("4096"), Ok(4096));
+        assert_eq!(size("4096B"), Ok(4096));
+    }
+
+    #[test]
+    fn suffixed() {
+        assert_eq!(size("512K"), Ok(512 << 10));
+        assert_eq!(size("64MiB"), Ok(64 << 20));
+        assert_eq!(size("2GB"), Ok(2 << 30));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(size("0").is_err());
+        assert!(size("1.5M").is_err());
+        assert!(size("-1K").is_err());
+        assert!(size("big").is_err());
+        assert!(size("99999999999G").is_err());
+    }
+}
+
+mod cpus_tests {
+    use super::*;
+
+    fn cpus(value: &str) -> CpuSelection {
+        parse_cpus(value.to_owned()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn all() {
+        assert_eq!(parse_cpus("all".to_owned()), Ok(None));
+    }
+
+    #[test]
+    fn list() {
+        let cpus = cpus("0,2,4-7");
+        for id in [0, 2, 4, 5, 6, 7] {
+            assert!(cpus.contains(&CpuId::from(id)));
+        }
+        for id in [1, 3, 8] {
+            assert!(!cpus.contains(&CpuId::from(id)));
+        }
+    }
+
+    /// the kernel's cpu lists use the same format, and end with a newline.
+    #[test]
+    fn kernel_list() {
+        let cpus = "0-2,4\n".parse::<CpuSelection>().unwrap();
+        let ids = cpus.ids().map(|id| id.get()).collect::<Vec<_>>();
+        assert_eq!(ids, [0, 1, 2, 4]);
+    }
+
+    #[test]
+    fn prefixed() {
+        assert!(cpus("cpu3").contains(&CpuId::from(3)));
+    }
+
+    #[test]
+    fn big_range() {
+        let cpus = cpus("0-4294967295");
+        assert!(cpus.contains(&CpuId::from(511)));
+    }
+
+    #[test]
+    fn descending() {
+        assert!(parse_cpus("7-4".to_owned()).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_cpus("zero".to_owned()).is_err());
+        assert!(parse_cpus("".to_owned()).is_err());
+        assert!(parse_cpus("1,,2".to_owned()).is_err());
+    }
+}
@@ -1,57 +1,308 @@
 //! a compact cpu monitor.
+//!
+//! tach reads kernel statistics from `/proc/stat`, and measures how each cpu spent its time
+//! between two readings. the [`Sentinel`] takes these readings, and yields a [`Recording`] of
+//! each interval.
+//!
+//! ```
+//! use {
+//!     std::time::Duration,
+//!     tach::{
+//!         Sentinel,
+//!         source::mock::{MockStatClock, MockStatFile},
+//!     },
+//! };
+//!
+//! // a system with one cpu, which was busy for half of a one second interval.
+//! let source = MockStatFile::new([
+//!     "cpu 0 0 0 0\ncpu0 0 0 0 0\n",
+//!     "cpu 50 0 0 50\ncpu0 50 0 0 50\n",
+//! ]);
+//! let clock = MockStatClock::ticking(Duration::from_secs(1), 2);
+//! let mut sentinel = Sentinel::from_parts(clock, source);
+//!
+//! // the first observation has nothing to compare against.
+//! assert!(sentinel.observe()?.is_none());
+//!
+//! let recording = sentinel.observe()?.expect("second observation yields a recording");
+//! assert_eq!(recording.system().percentage(), 50);
+//! # Ok::<(), tach::StatReadError>(())
+//! ```
+//!
+//! to observe the running system, use [`Sentinel::new()`], which reads `/proc/stat` and the
+//! system clock.
+
+#![warn(missing_docs)]
+
+pub use self::{
+    sentinel::{Recording, Sentinel},
+    source::{Clock, ProcStatFile, StatsSource, SystemClock},
+    stat::{
+        Accounting, Counters, CpuId, CpuTime, Entry, EntryParseError, Measurement, Rates, Snapshot,
+        StatReadError, State, TickRate, UserHz,
+    },
+};
 
 use {
-    self::sentinel::{Recording, Sentinel},
+    self::{
+        cli::{Destination, Options, Output},
+        output::Emit,
+    },
     std::{
-        io::{self, Write},
-        time::Duration,
+        io::{self, IsTerminal, Write},
+        path::PathBuf,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::{Duration, Instant},
     },
 };
 
+#[doc(hidden)]
+pub mod capture;
+
+/// command-line options.
+///
+/// this is used by the `tach` binary, and is not part of the library's stable api.
+#[doc(hidden)]
+pub mod cli;
+
 /// a meter displaying cpu usage.
 mod meter;
 
+/// line-oriented output of recordings.
+///
+/// this is used by the `tach` binary, and is not part of the library's stable api.
+#[doc(hidden)]
+pub mod output;
+
 /// a stream of statistics measurements.
-mod sentinel;
+pub mod sentinel;
 
 /// abstracts over i/o sources.
-mod source;
+pub mod source;
 
 /// kernel statistics facilities.
 ///
 /// this file provides tools to interact with `/proc/stat`.
-mod stat;
+pub mod stat;
 
 /// the tui window.
 mod window;
 
 /// an instance of the `tach` application.
+///
+/// this is used by the `tach` binary, and is not part of the library's stable api.
+#[doc(hidden)]
 pub struct App {
     /// the sentinel, observing kernel statistics.
     sentinel: Sentinel,
+    /// the application's options.
+    options: Options,
+    /// set when a signal asks the application to shut down.
+    shutdown: Arc<AtomicBool>,
 }
 
 /// A boxed error.
 type Error = Box<dyn std::error::Error>;
 
-/// === impl App ===
+// === impl App ===
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new(Options::default())
+    }
+}
 
 impl App {
     /// initializes a new application.
-    pub fn new() -> Self {
+    pub fn new(options: Options) -> Self {
+        let sentinel = Sentinel::new().with_accounting(options.accounting);
+        let sentinel = match options.tick_rate {
+            Some(tick_rate) => sentinel.with_tick_rate(tick_rate),
+            None => sentinel,
+        };
+
         Self {
-            sentinel: Sentinel::new(),
+            sentinel,
+            options,
+            shutdown: Arc::default(),
         }
     }
 
+    /// how often to check whether a signal has asked the application to shut down.
+    const TICK: Duration = Duration::from_millis(100);
+
     /// runs the application.
+    ///
+    /// this returns once the requested number of samples have been taken, or once the
+    /// application is asked to shut down by `SIGHUP`, `SIGINT`, or `SIGTERM`.
     pub fn run(self) -> Result<(), Error> {
-        self.tui().map_err(Into::into)
+        self.handle_signals()?;
+        if let Some(path) = self.options.record.clone() {
+            return self.record(path);
+        }
+
+        let to = self.options.to.clone();
+        match self.options.output {
+            Output::Auto if to == Destination::Stdout && io::stdout().is_terminal() => self.tui(),
+            Output::Auto | Output::Text => self.stream(output::Text::new(output::open(&to)?)),
+            Output::Tui => self.tui(),
+            Output::Json => self.stream(output::Json::new(output::open(&to)?)),
+            Output::Prometheus => {
+                let exporter = output::Prometheus::new();
+                exporter.serve(std::net::TcpListener::bind(self.options.listen)?)?;
+                self.stream(exporter)
+            }
+            Output::Csv => {
+                let csv = output::Csv::new(output::open(&to)?, self.options.csv_layout);
+                let csv = match self.possible_cpus() {
+                    Some(cpus) => csv.with_cpus(cpus),
+                    None => csv,
+                };
+                // a file being appended to keeps its header, and its columns.
+                match &to {
+                    Destination::File(path) => self.stream(csv.resume(path)?),
+                    _ => self.stream(csv),
+                }
+            }
+            Output::Influx => self.stream(output::Influx::new(output::open(&to)?)),
+            Output::Statsd | Output::Dogstatsd => {
+                let statsd = match &to {
+                    Destination::Stdout => output::Statsd::connect("127.0.0.1", 8125)?,
+                    Destination::Udp { host, port } => output::Statsd::connect(host, *port)?,
+                    Destination::File(_) | Destination::Http { .. } => {
+                        return Err("statsd gauges can only be sent to `udp://host:port`".into());
+                    }
+                };
+                let statsd = statsd.with_prefix(self.options.statsd_prefix.clone());
+                let statsd = match self.options.output {
+                    Output::Dogstatsd => statsd.with_tags(self.options.statsd_tags.clone()),
+                    _ => statsd,
+                };
+                self.stream(statsd)
+            }
+            Output::Otlp => {
+                let otlp = match to {
+                    Destination::Stdout => output::Otlp::new("127.0.0.1", 4318, "/v1/metrics"),
+                    Destination::Http { host, port, path } => output::Otlp::new(host, port, path),
+                    Destination::File(_) | Destination::Udp { .. } => {
+                        return Err(
+                            "otlp metrics can only be sent to `http://host:port/path`".into()
+                        );
+                    }
+                };
+                let otlp = otlp.with_encoding(self.options.otlp_encoding);
+                self.stream(otlp)
+            }
+            Output::Tmux | Output::I3bar | Output::Waybar => {
+                let protocol = match self.options.output {
+                    Output::Tmux => output::BarProtocol::Tmux,
+                    Output::I3bar => output::BarProtocol::I3bar,
+                    _ => output::BarProtocol::Waybar,
+                };
+                let bar = output::Bar::new(output::open(&to)?, protocol)
+                    .with_scope(self.options.bar_scope)
+                    .with_meter(self.options.meter_width, self.options.meter_align);
+                self.stream(bar)
+            }
+        }
+    }
+
+    /// returns the selected cpus that this system could ever bring online, if this is known.
+    fn possible_cpus(&self) -> Option<Vec<CpuId>> {
+        let possible = std::fs::read_to_string("/sys/devices/system/cpu/possible").ok()?;
+        let possible = possible.parse::<cli::CpuSelection>().ok()?;
+        let selected = |id: &CpuId| {
+            self.options
+                .cpus
+                .as_ref()
+                .is_none_or(|cpus| cpus.contains(id))
+        };
+
+        Some(possible.ids().filter(selected).collect())
+    }
+
+    /// emits each recording, until the requested number of samples have been taken.
+    fn stream(mut self, mut emitter: impl Emit) -> Result<(), Error> {
+        let mut taken = 0;
+        while !self.finished(taken) && !self.shutdown_requested() {
+            if let Some(recording) = self.observe()? {
+                emitter.emit(&recording)?;
+                taken += 1;
+            }
+            if !self.finished(taken) {
+                self.sleep();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// appends each raw read of the kernel's statistics to the capture file at `path`, until the
+    /// requested number of reads have been taken.
+    fn record(self, path: PathBuf) -> Result<(), Error> {
+        let tick_rate = self.options.tick_rate.unwrap_or_default();
+        let header = capture::Header::new(tick_rate);
+        let mut writer = capture::Writer::create(path, header, self.options.rotation)?;
+
+        let mut taken = 0;
+        while !self.finished(taken) && !self.shutdown_requested() {
+            writer.write(&capture::Frame::read(&ProcStatFile)?)?;
+            taken += 1;
+            if !self.finished(taken) {
+                self.sleep();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// returns a recording of the selected cpus, since this was last called.
+    fn observe(&mut self) -> Result<Option<Recording>, StatReadError> {
+        let Self {
+            sentinel, options, ..
+        } = self;
+
+        let mut recording = sentinel.observe()?;
+        if let (Some(recording), Some(cpus)) = (recording.as_mut(), &options.cpus) {
+            recording.retain_cpus(|id| cpus.contains(id));
+        }
+
+        Ok(recording)
+    }
+
+    /// returns `true` if the requested number of samples have been taken.
+    fn finished(&self, taken: u64) -> bool {
+        self.options.count.is_some_and(|count| taken >= count)
+    }
+
+    /// sleeps until another measurement should be taken, or the application should shut down.
+    fn sleep(&self) {
+        let deadline = Instant::now() + self.options.interval;
+        while !self.shutdown_requested() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            std::thread::sleep(remaining.min(Self::TICK));
+        }
+    }
+
+    /// asks to be notified when a signal asks the application to shut down.
+    fn handle_signals(&self) -> io::Result<()> {
+        use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+        for signal in [SIGHUP, SIGINT, SIGTERM] {
+            signal_hook::flag::register(signal, Arc::clone(&self.shutdown))?;
+        }
+
+        Ok(())
     }
 
-    /// sleeps until another measurement should be taken.
-    fn sleep() {
-        const INTERVAL: Duration = Duration::from_secs(1);
-        std::thread::sleep(INTERVAL);
+    /// returns `true` if a signal has asked the application to shut down.
+    fn shutdown_requested(&self) -> bool {
+        self.shutdown.load(Ordering::Relaxed)
     }
 }
@@ -1,9 +1,41 @@
 //! a compact cpu monitor.
 
-type Error = Box<dyn std::error::Error>;
+use {
+    std::{io, process::ExitCode},
+    tach::cli::{Command, USAGE},
+};
 
-fn main() -> Result<(), Error> {
-    let app = tach::App::new();
-    app.run()?;
-    unreachable!();
+fn main() -> ExitCode {
+    let options = match Command::parse(std::env::args().skip(1)) {
+        Ok(Command::Run(options)) => *options,
+        Ok(Command::Help) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("tach {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("tach: {error}");
+            eprintln!("try `tach --help` for more information.");
+            return ExitCode::from(2);
+        }
+    };
+
+    match tach::App::new(options).run() {
+        Ok(()) => ExitCode::SUCCESS,
+        // whatever was reading the output has gone away, as when piped to `head`.
+        Err(error)
+            if error
+                .downcast_ref::<io::Error>()
+                .is_some_and(|error| error.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("tach: {error}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -1,17 +1,46 @@
-#![allow(dead_code, reason = "TODO(kate): refactoring display")]
+use std::fmt::{self, Display};
 
-use std::{
-    fmt::Display,
-    io::{self, Write},
-};
+#[cfg(test)]
+mod tests;
 
+/// a meter showing a fraction, drawn with braille characters.
+#[derive(Clone, Copy, Debug)]
 pub struct Meter {
+    /// the fraction to show, from zero to one.
     pub value: f64,
+    /// the width of the meter, in cells.
     pub width: usize,
+    /// where the filled cells are placed.
+    pub align: Align,
+}
+
+/// where the filled part of a meter is placed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Align {
+    /// fill from the left.
+    #[default]
+    Left,
+    /// fill from the right.
+    Right,
+    /// fill outwards from the middle.
+    Center,
+}
+
+/// a sparkline showing a history of fractions, drawn with braille characters.
+///
+/// each cell holds two values side by side, as columns of zero to four dots. the most recent
+/// values are drawn at the right.
+#[derive(Clone, Debug)]
+pub struct Sparkline {
+    /// the values to show, oldest first, each from zero to one.
+    values: Vec<f64>,
+    /// the width of the sparkline, in cells.
+    width: usize,
 }
 
 /// a reading is a list of cells.
 // xxx rename this to meter
+#[derive(Debug)]
 pub struct Reading {
     cells: Vec<Cell>,
 }
@@ -30,34 +59,118 @@ enum Cell {
     Eight,
 }
 
-/// === impl Meter ===
+// === impl Meter ===
 
 impl Meter {
-    // XXX: a simple, hacky meter.
-    pub fn draw(&self, writer: &mut impl Write) -> io::Result<()> {
-        let Reading { cells } = placeholder(self.value, self.width);
-        let reading = Reading {
-            cells: middle_fill(cells.into_iter()),
+    /// returns a left-aligned meter showing `value`, `width` cells wide.
+    ///
+    /// values outside of `0.0..=1.0` are clamped, and `NaN` is shown as an empty meter.
+    pub fn new(value: f64, width: usize) -> Self {
+        let value = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0)
         };
-        writer.write(reading.to_string().as_bytes())?;
+
+        Self {
+            value,
+            width,
+            align: Align::default(),
+        }
+    }
+
+    /// places the filled cells according to `align`.
+    pub fn with_align(self, align: Align) -> Self {
+        Self { align, ..self }
+    }
+
+    /// returns the cells of this meter.
+    pub fn reading(&self) -> Reading {
+        let Self {
+            value,
+            width,
+            align,
+        } = *self;
+
+        let Reading { cells } = fill(value, width);
+        let cells = match align {
+            Align::Left => cells,
+            Align::Right => cells.into_iter().rev().collect(),
+            Align::Center => middle_fill(cells.into_iter()),
+        };
+
+        Reading { cells }
+    }
+}
+
+impl Display for Meter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.reading().fmt(f)
+    }
+}
+
+// === impl Sparkline ===
+
+impl Sparkline {
+    /// the number of values shown in each cell.
+    pub const VALUES_PER_CELL: usize = 2;
+
+    /// returns a sparkline `width` cells wide, showing the most recent of `values`.
+    ///
+    /// values outside of `0.0..=1.0` are clamped, and `NaN` is shown as zero.
+    pub fn new(values: impl IntoIterator<Item = f64>, width: usize) -> Self {
+        let mut values = values
+            .into_iter()
+            .map(|value| {
+                if value.is_nan() {
+                    0.0
+                } else {
+                    value.clamp(0.0, 1.0)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let capacity = width * Self::VALUES_PER_CELL;
+        values.drain(..values.len().saturating_sub(capacity));
+
+        Self { values, width }
+    }
+}
+
+impl Display for Sparkline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use {chars::dots, std::fmt::Write};
+
+        let Self { values, width } = self;
+        let level = |value: f64| (value * dots::HEIGHT as f64).round() as usize;
+
+        // pad the start with empty values, so that the most recent value is at the right.
+        let padding = width * Self::VALUES_PER_CELL - values.len();
+        let padded = std::iter::repeat_n(0.0, padding).chain(values.iter().copied());
+        let levels = padded.map(level).collect::<Vec<_>>();
+
+        for pair in levels.chunks_exact(Self::VALUES_PER_CELL) {
+            let bits = dots::LEFT[pair[0]] | dots::RIGHT[pair[1]];
+            let c = char::from_u32(u32::from(chars::levels::ZERO) + u32::from(bits))
+                .expect("braille patterns are valid characters");
+            f.write_char(c)?;
+        }
+
         Ok(())
     }
 }
 
-fn placeholder(percentage: f64, width: usize) -> Reading {
-    assert!(percentage >= 0.0);
-    assert!(percentage <= 1.0);
-    assert!(width > 0);
+/// returns a reading `width` cells wide, filled from the left.
+fn fill(fraction: f64, width: usize) -> Reading {
+    assert!(fraction >= 0.0);
+    assert!(fraction <= 1.0);
 
     // how many dots should we display?
-    let amount = {
-        let resolution = Cell::RESOLUTION as usize * width;
-        let amount = resolution as f64 * percentage;
-        amount.round() as u8
-    };
-
-    let filled = (amount / Cell::RESOLUTION).into();
-    let rem = Cell::try_from(amount % Cell::RESOLUTION).expect("remainder should not panic");
+    let resolution = usize::from(Cell::RESOLUTION);
+    let amount = ((resolution * width) as f64 * fraction).round() as usize;
+
+    let filled = amount / resolution;
+    let rem = Cell::try_from((amount % resolution) as u8).expect("remainder should not panic");
     assert!(filled <= width);
 
     let cells = std::iter::repeat_n(Cell::Eight, filled)
@@ -69,12 +182,13 @@ fn placeholder(percentage: f64, width: usize) -> Reading {
     Reading { cells }
 }
 
-fn middle_fill(mut cells: impl Iterator<Item = Cell>) -> Vec<Cell> {
+/// rearranges cells so that the first is in the middle, and the rest spread out to either side.
+fn middle_fill(cells: impl Iterator<Item = Cell>) -> Vec<Cell> {
     use std::collections::VecDeque;
     let mut new = VecDeque::with_capacity(cells.size_hint().0);
     let mut flip = false;
 
-    while let Some(next) = cells.next() {
+    for next in cells {
         if flip {
             new.push_front(next);
         } else {
@@ -86,8 +200,10 @@ fn middle_fill(mut cells: impl Iterator<Item = Cell>) -> Vec<Cell> {
     new.into()
 }
 
+// === impl Reading ===
+
 impl Display for Reading {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let Self { cells } = self;
         for c in cells.iter().map(Cell::as_char) {
             use std::fmt::Write;
@@ -97,6 +213,8 @@ impl Display for Reading {
     }
 }
 
+// === impl Cell ===
+
 impl TryFrom<u8> for Cell {
     type Error = u8;
     fn try_from(value: u8) -> Result<Self, Self::Error> {
@@ -125,25 +243,38 @@ impl Cell {
     fn as_char(&self) -> char {
         use {Cell::*, chars::*};
         match self {
-            Zero => placeholder::ZERO,
-            One => placeholder::ONE,
-            Two => placeholder::TWO,
-            Three => placeholder::THREE,
-            Four => placeholder::FOUR,
-            Five => placeholder::FIVE,
-            Six => placeholder::SIX,
-            Seven => placeholder::SEVEN,
-            Eight => placeholder::EIGHT,
+            Zero => levels::ZERO,
+            One => levels::ONE,
+            Two => levels::TWO,
+            Three => levels::THREE,
+            Four => levels::FOUR,
+            Five => levels::FIVE,
+            Six => levels::SIX,
+            Seven => levels::SEVEN,
+            Eight => levels::EIGHT,
         }
     }
 }
 
-/// characters for drawing a [`Meter`].
+/// characters for drawing a [`Meter`] or [`Sparkline`].
 ///
 /// [unicode]: https://www.unicode.org/charts/PDF/U2800.pdf
 #[rustfmt::skip]
 mod chars {
-    pub mod placeholder {
+    /// dots in a braille cell, added to the blank pattern [`levels::ZERO`].
+    ///
+    /// each table is indexed by the number of dots to fill, from the bottom of the column up.
+    pub mod dots {
+        /// the number of dots in a column.
+        pub const HEIGHT: usize = 4;
+
+        /// the left column of dots.
+        pub const LEFT: [u8; HEIGHT + 1] = [0x00, 0x40, 0x44, 0x46, 0x47];
+        /// the right column of dots.
+        pub const RIGHT: [u8; HEIGHT + 1] = [0x00, 0x80, 0xA0, 0xB0, 0xB8];
+    }
+
+    pub mod levels {
         pub const ZERO: char = '\u{2800}';  // `⠀`
         pub const ONE: char = '\u{2840}';   // `⡀`
         pub const TWO: char = '\u{28C0}';   // `⣀`
@@ -155,96 +286,3 @@ mod chars {
         pub const EIGHT: char = '\u{28FF}'; // `⣿`
     }
 }
-
-#[cfg(test)]
-mod placeholder_tests {
-    use super::*;
-
-    #[test]
-    fn zero_width_one() {
-        let (percentage, width) = (0.0, 1);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⠀");
-    }
-
-    #[test]
-    fn zero_width_eight() {
-        let (percentage, width) = (0.0, 8);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⠀⠀⠀⠀⠀⠀⠀⠀");
-    }
-
-    #[test]
-    fn zero_width_ten() {
-        let (percentage, width) = (0.0, 10);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀");
-    }
-
-    #[test]
-    fn one_eighth_width_one() {
-        let (percentage, width) = (0.125, 1);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⡀");
-    }
-
-    #[test]
-    fn one_quarter_width_one() {
-        let (percentage, width) = (0.25, 1);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⣀");
-    }
-
-    #[test]
-    fn one_quarter_width_eight() {
-        let (percentage, width) = (0.25, 8);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⣿⣿⠀⠀⠀⠀⠀⠀");
-    }
-
-    #[test]
-    fn three_eighths_width_one() {
-        let (percentage, width) = (0.325, 1);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⣄");
-    }
-
-    #[test]
-    fn half_width_eight() {
-        let (percentage, width) = (0.50, 8);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⣿⣿⣿⣿⠀⠀⠀⠀");
-    }
-
-    #[test]
-    fn three_quarter_width_eight() {
-        let (percentage, width) = (0.75, 8);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⣿⣿⣿⣿⣿⣿⠀⠀");
-    }
-
-    #[test]
-    fn seven_eighths_width_one() {
-        let (percentage, width) = (0.875, 1);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⣷");
-    }
-
-    #[test]
-    fn full_width_eight() {
-        let (percentage, width) = (1.00, 8);
-        let reading = placeholder(percentage, width);
-        let s = reading.to_string();
-        assert_eq!(s, "⣿⣿⣿⣿⣿⣿⣿⣿");
-    }
-}
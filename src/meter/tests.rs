@@ -0,0 +1,187 @@
+use super::*;
+
+mod fill_tests {
+    use super::*;
+
+    #[test]
+    fn zero_width_one() {
+        let (fraction, width) = (0.0, 1);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⠀");
+    }
+
+    #[test]
+    fn zero_width_eight() {
+        let (fraction, width) = (0.0, 8);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⠀⠀⠀⠀⠀⠀⠀⠀");
+    }
+
+    #[test]
+    fn zero_width_ten() {
+        let (fraction, width) = (0.0, 10);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀");
+    }
+
+    #[test]
+    fn one_eighth_width_one() {
+        let (fraction, width) = (0.125, 1);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⡀");
+    }
+
+    #[test]
+    fn one_quarter_width_one() {
+        let (fraction, width) = (0.25, 1);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⣀");
+    }
+
+    #[test]
+    fn one_quarter_width_eight() {
+        let (fraction, width) = (0.25, 8);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⣿⣿⠀⠀⠀⠀⠀⠀");
+    }
+
+    #[test]
+    fn three_eighths_width_one() {
+        let (fraction, width) = (0.325, 1);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⣄");
+    }
+
+    #[test]
+    fn half_width_eight() {
+        let (fraction, width) = (0.50, 8);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⣿⣿⣿⣿⠀⠀⠀⠀");
+    }
+
+    #[test]
+    fn three_quarter_width_eight() {
+        let (fraction, width) = (0.75, 8);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⣿⣿⣿⣿⣿⣿⠀⠀");
+    }
+
+    #[test]
+    fn seven_eighths_width_one() {
+        let (fraction, width) = (0.875, 1);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⣷");
+    }
+
+    #[test]
+    fn full_width_eight() {
+        let (fraction, width) = (1.00, 8);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⣿⣿⣿⣿⣿⣿⣿⣿");
+    }
+
+    /// wide meters have more dots than fit in a `u8`.
+    #[test]
+    fn full_width_forty() {
+        let (fraction, width) = (1.00, 40);
+        let reading = fill(fraction, width);
+        let s = reading.to_string();
+        assert_eq!(s, "⣿".repeat(40));
+    }
+}
+
+mod meter_tests {
+    use super::*;
+
+    #[test]
+    fn left() {
+        let meter = Meter::new(0.375, 4).with_align(Align::Left);
+        assert_eq!(meter.to_string(), "⣿⣤⠀⠀");
+    }
+
+    #[test]
+    fn right() {
+        let meter = Meter::new(0.375, 4).with_align(Align::Right);
+        assert_eq!(meter.to_string(), "⠀⠀⣤⣿");
+    }
+
+    #[test]
+    fn center() {
+        let meter = Meter::new(0.5, 4).with_align(Align::Center);
+        assert_eq!(meter.to_string(), "⠀⣿⣿⠀");
+    }
+
+    #[test]
+    fn center_odd_width() {
+        let meter = Meter::new(1.0 / 3.0, 3).with_align(Align::Center);
+        assert_eq!(meter.to_string(), "⠀⣿⠀");
+    }
+
+    #[test]
+    fn width_is_respected() {
+        for align in [Align::Left, Align::Right, Align::Center] {
+            for width in [1, 5, 8, 13] {
+                let meter = Meter::new(0.7, width).with_align(align);
+                assert_eq!(meter.to_string().chars().count(), width);
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_is_clamped() {
+        assert_eq!(Meter::new(1.5, 2).to_string(), "⣿⣿");
+        assert_eq!(Meter::new(-0.5, 2).to_string(), "⠀⠀");
+        assert_eq!(Meter::new(f64::NAN, 2).to_string(), "⠀⠀");
+    }
+}
+
+mod sparkline_tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(Sparkline::new([], 3).to_string(), "⠀⠀⠀");
+    }
+
+    #[test]
+    fn levels() {
+        let sparkline = Sparkline::new([0.0, 0.25, 0.5, 0.75, 1.0, 1.0], 3);
+        assert_eq!(sparkline.to_string(), "⢀⣴⣿");
+    }
+
+    #[test]
+    fn two_values_per_cell() {
+        assert_eq!(Sparkline::new([1.0, 0.0], 1).to_string(), "⡇");
+        assert_eq!(Sparkline::new([0.0, 1.0], 1).to_string(), "⢸");
+    }
+
+    /// the most recent values are drawn at the right.
+    #[test]
+    fn right_aligned() {
+        assert_eq!(Sparkline::new([1.0], 2).to_string(), "⠀⢸");
+    }
+
+    /// older values scroll off the left.
+    #[test]
+    fn scrolls() {
+        let sparkline = Sparkline::new([1.0, 1.0, 0.0, 0.0, 0.0, 1.0], 2);
+        assert_eq!(sparkline.to_string(), "⠀⢸");
+    }
+
+    #[test]
+    fn out_of_range_is_clamped() {
+        let sparkline = Sparkline::new([-1.0, 2.0, f64::NAN, 0.5], 2);
+        assert_eq!(sparkline.to_string(), "⢸⢠");
+    }
+}
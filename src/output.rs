@@ -0,0 +1,170 @@
+pub use self::{
+    bar::{Bar, BarProtocol, BarScope},
+    csv::Csv,
+    influx::Influx,
+    json::Json,
+    otlp::Otlp,
+    prometheus::Prometheus,
+    sink::open,
+    statsd::Statsd,
+    text::Text,
+};
+
+use {
+    crate::{
+        sentinel::Recording,
+        stat::{Measurement, State},
+    },
+    std::{io, time::SystemTime},
+};
+
+mod bar;
+mod csv;
+mod http;
+mod influx;
+pub mod json;
+pub mod otlp;
+mod prometheus;
+mod sink;
+mod statsd;
+mod text;
+
+#[cfg(test)]
+mod tests;
+
+/// a sink for [`Recording`]s.
+pub trait Emit {
+    /// emits a recording.
+    fn emit(&mut self, recording: &Recording) -> io::Result<()>;
+}
+
+/// counts what a network emitter could not send.
+///
+/// a collector that is missing is not an error, so that sampling continues until it is back. the
+/// first failure is reported on stderr, and the number of failures once the count is dropped.
+struct Dropped {
+    /// what is being sent, in the plural.
+    what: &'static str,
+    count: u64,
+}
+
+/// the fields written for each measurement, by the csv, influx, and statsd emitters.
+///
+/// `None` is the fraction of time that was busy.
+const FIELDS: [(&str, Option<State>); 11] = [
+    ("busy", None),
+    ("user", Some(State::User)),
+    ("nice", Some(State::Nice)),
+    ("system", Some(State::System)),
+    ("iowait", Some(State::Iowait)),
+    ("irq", Some(State::Irq)),
+    ("softirq", Some(State::Softirq)),
+    ("steal", Some(State::Steal)),
+    ("guest", Some(State::Guest)),
+    ("guest_nice", Some(State::GuestNice)),
+    ("idle", Some(State::Idle)),
+];
+
+// === impl Dropped ===
+
+impl Dropped {
+    /// returns an empty count of `what`, named in the plural.
+    fn new(what: &'static str) -> Self {
+        Self { what, count: 0 }
+    }
+
+    /// counts a failure to send, reporting it if it is the first.
+    fn record(&mut self, error: &io::Error) {
+        if self.count == 0 {
+            eprintln!(
+                "tach: could not send {}, and will keep trying: {error}",
+                self.what
+            );
+        }
+        self.count += 1;
+    }
+
+    /// returns the number of failures.
+    fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl Drop for Dropped {
+    fn drop(&mut self) {
+        if self.count > 0 {
+            eprintln!("tach: {} that could not be sent: {}", self.what, self.count);
+        }
+    }
+}
+
+/// returns the percentage of time in a measurement that was spent in `state`, or that was busy.
+fn percentage(measurement: &Measurement, state: Option<State>) -> Option<f64> {
+    let fraction = match state {
+        Some(state) => measurement.fraction(state),
+        None => measurement.ratio(),
+    };
+    fraction.map(|fraction| fraction * 100.0)
+}
+
+/// returns this system's hostname, or `unknown` if it cannot be found.
+pub(crate) fn hostname() -> String {
+    let mut buffer = [0u8; 256];
+    // SAFETY: `gethostname` writes at most `buffer.len()` bytes to `buffer`.
+    if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } != 0 {
+        return "unknown".to_owned();
+    }
+
+    // the name may not be terminated if it was truncated.
+    let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    String::from_utf8_lossy(&buffer[..len]).into_owned()
+}
+
+/// formats a string as json, escaping quotes, backslashes, and control characters.
+fn json_string(s: &str) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str(r#"\""#),
+            '\\' => out.push_str(r"\\"),
+            '\n' => out.push_str(r"\n"),
+            c if c.is_control() => {
+                let _ = write!(out, r"\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// formats `time` as an rfc 3339 timestamp in utc, with microsecond precision.
+fn rfc3339(time: SystemTime) -> String {
+    let since = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // convert days since the epoch to a civil date, using howard hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:06}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        since.subsec_micros(),
+    )
+}
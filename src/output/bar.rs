@@ -0,0 +1,221 @@
+use {
+    super::Emit,
+    crate::{
+        meter::{Align, Meter, Sparkline},
+        sentinel::Recording,
+        stat::Measurement,
+    },
+    std::io::{self, Write},
+};
+
+/// writes each recording as a status bar's text, in one of several [protocols](BarProtocol).
+///
+/// the text is a meter for the system as a whole, or a column of dots for each cpu, followed by
+/// the percentage of time that the system was busy. see [`Bar::with_scope()`].
+///
+/// the text is colored by how busy the system was: below half is normal, below four fifths is a
+/// warning, and anything busier is critical.
+pub struct Bar<W> {
+    writer: W,
+    /// how the text is written.
+    protocol: BarProtocol,
+    /// what the text shows.
+    scope: BarScope,
+    /// the width of the system's meter, in cells.
+    width: usize,
+    /// where the filled part of the system's meter is placed.
+    align: Align,
+    /// whether anything has been written yet.
+    started: bool,
+}
+
+/// how a status bar's text is written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BarProtocol {
+    /// a line of text with tmux format escapes, such as `#[fg=green]`, for `status-right`.
+    Tmux,
+    /// the i3bar protocol, which is also read by swaybar.
+    ///
+    /// a header is written first, followed by an endless json array with a block for each
+    /// recording.
+    I3bar,
+    /// a json object per line, for a waybar custom module with `"return-type": "json"`.
+    Waybar,
+}
+
+/// what status bars show.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BarScope {
+    /// a meter for the system as a whole.
+    #[default]
+    System,
+    /// a column of dots for each cpu.
+    Cpus,
+}
+
+/// how busy the system was.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Level {
+    Normal,
+    Warning,
+    Critical,
+}
+
+// === impl Bar ===
+
+impl<W: Write> Bar<W> {
+    /// returns a new status bar emitter, writing to `writer` in the given `protocol`.
+    ///
+    /// by default, the system is shown as a left-aligned meter, 8 cells wide.
+    pub fn new(writer: W, protocol: BarProtocol) -> Self {
+        Self {
+            writer,
+            protocol,
+            scope: BarScope::default(),
+            width: 8,
+            align: Align::default(),
+            started: false,
+        }
+    }
+
+    /// shows the system as a whole, or each cpu, according to `scope`.
+    pub fn with_scope(self, scope: BarScope) -> Self {
+        Self { scope, ..self }
+    }
+
+    /// draws the system's meter `width` cells wide, placing the filled part according to `align`.
+    pub fn with_meter(self, width: usize, align: Align) -> Self {
+        Self {
+            width,
+            align,
+            ..self
+        }
+    }
+
+    /// returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// returns the text for a recording: a meter or columns, followed by a percentage.
+    fn text(&self, recording: &Recording) -> String {
+        let fraction = |measurement: &Measurement| measurement.ratio().unwrap_or(0.0);
+        let meter = match self.scope {
+            BarScope::System => Meter::new(fraction(recording.system()), self.width)
+                .with_align(self.align)
+                .to_string(),
+            BarScope::Cpus => {
+                let width = recording.cpus().len().div_ceil(Sparkline::VALUES_PER_CELL);
+                Sparkline::new(recording.cpus().values().map(fraction), width).to_string()
+            }
+        };
+
+        format!("{meter} {:>3}%", recording.system().percentage())
+    }
+
+    /// returns the percentage of time that each cpu was busy, one per line.
+    fn tooltip(recording: &Recording) -> String {
+        let mut tooltip = format!("cpu  {:>3}%", recording.system().percentage());
+        for (cpu, measurement) in recording.cpus() {
+            let label = format!("cpu{}", cpu.get());
+            tooltip += &format!("\n{label:<4} {:>3}%", measurement.percentage());
+        }
+        tooltip
+    }
+
+    /// writes a recording.
+    fn write(&mut self, recording: &Recording) -> io::Result<()> {
+        let text = self.text(recording);
+        let level = Level::of(recording.system());
+        let Self {
+            writer,
+            protocol,
+            started,
+            ..
+        } = self;
+
+        match protocol {
+            BarProtocol::Tmux => writeln!(writer, "#[fg={}]{text}#[default]", level.tmux())?,
+            BarProtocol::I3bar => {
+                // the header, and the start of the endless array of status lines.
+                match *started {
+                    false => write!(writer, "{{\"version\":1}}\n[\n")?,
+                    true => write!(writer, ",")?,
+                }
+                writeln!(
+                    writer,
+                    concat!(
+                        r#"[{{"name":"tach","full_text":{},"short_text":"{}%","#,
+                        r#""color":"{}","markup":"none"}}]"#,
+                    ),
+                    super::json_string(&text),
+                    recording.system().percentage(),
+                    level.i3bar(),
+                )?;
+            }
+            BarProtocol::Waybar => writeln!(
+                writer,
+                r#"{{"text":{},"tooltip":{},"class":"{}","percentage":{}}}"#,
+                super::json_string(&text),
+                super::json_string(&Self::tooltip(recording)),
+                level.class(),
+                recording.system().percentage(),
+            )?,
+        }
+
+        *started = true;
+        writer.flush()
+    }
+}
+
+impl<W: Write> Emit for Bar<W> {
+    fn emit(&mut self, recording: &Recording) -> io::Result<()> {
+        self.write(recording)
+    }
+}
+
+// === impl Level ===
+
+impl Level {
+    /// the fraction of time busy at which the system is shown as a warning.
+    const WARNING: f64 = 0.5;
+
+    /// the fraction of time busy at which the system is shown as critical.
+    const CRITICAL: f64 = 0.8;
+
+    /// returns how busy a measurement was. a measurement in which no time passed is normal.
+    fn of(measurement: &Measurement) -> Self {
+        match measurement.ratio().unwrap_or(0.0) {
+            ratio if ratio >= Self::CRITICAL => Self::Critical,
+            ratio if ratio >= Self::WARNING => Self::Warning,
+            _ => Self::Normal,
+        }
+    }
+
+    /// the css class given to waybar.
+    fn class(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+
+    /// the color given to i3bar, which matches i3status's defaults.
+    fn i3bar(self) -> &'static str {
+        match self {
+            Self::Normal => "#00FF00",
+            Self::Warning => "#FFFF00",
+            Self::Critical => "#FF0000",
+        }
+    }
+
+    /// the color given to tmux.
+    fn tmux(self) -> &'static str {
+        match self {
+            Self::Normal => "green",
+            Self::Warning => "yellow",
+            Self::Critical => "red",
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use {
+    super::{Emit, FIELDS},
+    crate::{
+        cli::CsvLayout,
+        sentinel::Recording,
+        stat::{CpuId, Measurement},
+    },
+    std::{
+        collections::BTreeSet,
+        fs::File,
+        io::{self, BufRead, BufReader, Write},
+        path::Path,
+        time::SystemTime,
+    },
+};
+
+/// writes recordings as comma-separated values, with a header row.
+///
+/// each measurement is written as the percentage of time that was busy, followed by the
+/// percentage of time spent in each state. states that were not reported, and intervals in which
+/// no time passed, are left empty.
+///
+/// in the [wide](CsvLayout::Wide) layout, the columns are fixed when the header is written, so
+/// that every row has the same columns. cpus that are offline are left empty, and cpus that were
+/// not known when the header was written are omitted. see [`Csv::with_cpus()`].
+///
+/// a file that already holds rows can be appended to; see [`Csv::resume()`].
+pub struct Csv<W> {
+    writer: W,
+    /// how rows are laid out.
+    layout: CsvLayout,
+    /// the cpus with columns in the wide layout, or `None` if the header has not been written.
+    columns: Option<BTreeSet<CpuId>>,
+    /// whether the header row has been written.
+    header: bool,
+}
+
+// === impl Csv ===
+
+impl<W: Write> Csv<W> {
+    /// returns a new csv emitter, writing to `writer`.
+    pub fn new(writer: W, layout: CsvLayout) -> Self {
+        Self {
+            writer,
+            layout,
+            columns: None,
+            header: false,
+        }
+    }
+
+    /// fixes the cpus given columns in the wide layout.
+    ///
+    /// by default, the columns are chosen from the cpus known when the first recording is
+    /// written. passing every possible cpu keeps the columns stable as cpus come and go.
+    pub fn with_cpus(self, cpus: impl IntoIterator<Item = CpuId>) -> Self {
+        Self {
+            columns: Some(cpus.into_iter().collect()),
+            ..self
+        }
+    }
+
+    /// continues the csv file at `path`, if it exists and is not empty.
+    ///
+    /// the file's header is not written again, and in the wide layout, its columns are kept. this
+    /// fails if the file's header was not written by tach in the same layout.
+    pub fn resume(self, path: &Path) -> io::Result<Self> {
+        let mut header = String::new();
+        match File::open(path) {
+            Ok(file) => BufReader::new(file).read_line(&mut header)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(self),
+            Err(error) => return Err(error),
+        };
+
+        match header.is_empty() {
+            true => Ok(self),
+            false => self.resume_header(header.trim_end()),
+        }
+    }
+
+    /// returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// continues after an existing `header` row.
+    fn resume_header(self, header: &str) -> io::Result<Self> {
+        let columns = match self.layout {
+            CsvLayout::Long => None,
+            // the first field of each cpu's columns is named for it.
+            CsvLayout::Wide => Some(
+                header
+                    .split(',')
+                    .filter_map(|column| column.strip_suffix(&format!("_{}", FIELDS[0].0)))
+                    .filter(|prefix| *prefix != "all")
+                    .map(|prefix| {
+                        prefix
+                            .strip_prefix("cpu")?
+                            .parse::<u32>()
+                            .ok()
+                            .map(CpuId::from)
+                    })
+                    .collect::<Option<BTreeSet<_>>>()
+                    .unwrap_or_default(),
+            ),
+        };
+
+        let expected = match &columns {
+            None => Self::long_header(),
+            Some(columns) => Self::wide_header(columns),
+        };
+        if header != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "cannot append to a csv file whose header does not match the layout",
+            ));
+        }
+
+        Ok(Self {
+            columns: columns.or(self.columns),
+            header: true,
+            ..self
+        })
+    }
+
+    /// returns the header row of the long layout.
+    fn long_header() -> String {
+        let mut header = "timestamp,interval,cpu".to_owned();
+        for (name, _) in FIELDS {
+            header += &format!(",{name}");
+        }
+        header + ",trustworthy"
+    }
+
+    /// returns the header row of the wide layout, with columns for the given cpus.
+    fn wide_header(columns: &BTreeSet<CpuId>) -> String {
+        let mut header = "timestamp,interval".to_owned();
+        let prefixes =
+            std::iter::once("all".to_owned()).chain(columns.iter().map(CpuId::to_string));
+        for prefix in prefixes {
+            for (name, _) in FIELDS {
+                header += &format!(",{prefix}_{name}");
+            }
+        }
+        header + ",trustworthy"
+    }
+
+    /// writes a recording, stamped with `time`.
+    fn write(&mut self, recording: &Recording, time: SystemTime) -> io::Result<()> {
+        let timestamp = super::rfc3339(time);
+        let interval = recording.elapsed().as_secs_f64();
+
+        match self.layout {
+            CsvLayout::Long => self.long(recording, &timestamp, interval)?,
+            CsvLayout::Wide => self.wide(recording, &timestamp, interval)?,
+        }
+
+        self.writer.flush()
+    }
+
+    /// writes a row for the system, and for each cpu.
+    fn long(&mut self, recording: &Recording, timestamp: &str, interval: f64) -> io::Result<()> {
+        let Self { writer, header, .. } = self;
+
+        if !*header {
+            writeln!(writer, "{}", Self::long_header())?;
+            *header = true;
+        }
+
+        let mut row = |cpu: &str, measurement: &Measurement| {
+            write!(writer, "{timestamp},{interval},{cpu}")?;
+            Self::fields(writer, Some(measurement))?;
+            writeln!(writer, ",{}", measurement.is_trustworthy())
+        };
+
+        row("all", recording.system())?;
+        for (cpu, measurement) in recording.cpus() {
+            row(&cpu.get().to_string(), measurement)?;
+        }
+
+        Ok(())
+    }
+
+    /// writes a single row, with columns for the system and for each cpu.
+    fn wide(&mut self, recording: &Recording, timestamp: &str, interval: f64) -> io::Result<()> {
+        let Self {
+            writer,
+            columns,
+            header,
+            ..
+        } = self;
+
+        let columns = columns.get_or_insert_with(|| {
+            recording
+                .cpus()
+                .keys()
+                .chain(recording.appeared())
+                .chain(recording.disappeared())
+                .copied()
+                .collect()
+        });
+
+        if !*header {
+            writeln!(writer, "{}", Self::wide_header(columns))?;
+            *header = true;
+        }
+
+        write!(writer, "{timestamp},{interval}")?;
+        Self::fields(writer, Some(recording.system()))?;
+        for cpu in columns.iter() {
+            Self::fields(writer, recording.cpus().get(cpu))?;
+        }
+
+        let trustworthy = std::iter::once(recording.system())
+            .chain(recording.cpus().values())
+            .all(Measurement::is_trustworthy);
+        writeln!(writer, ",{trustworthy}")
+    }
+
+    /// writes the fields of a measurement, each preceded by a comma.
+    ///
+    /// an absent measurement is written as empty fields.
+    fn fields(writer: &mut W, measurement: Option<&Measurement>) -> io::Result<()> {
+        for (_, state) in FIELDS {
+            let percentage =
+                measurement.and_then(|measurement| super::percentage(measurement, state));
+            match percentage {
+                Some(percentage) => write!(writer, ",{percentage:.3}")?,
+                None => write!(writer, ",")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Emit for Csv<W> {
+    fn emit(&mut self, recording: &Recording) -> io::Result<()> {
+        self.write(recording, SystemTime::now())
+    }
+}
@@ -0,0 +1,191 @@
+//! a minimal http/1.1 server, for serving metrics to scrapers, and client, for pushing them.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// a request's method and path.
+#[derive(Debug, Eq, PartialEq)]
+pub(super) struct Request {
+    /// the request method, such as `GET`.
+    pub(super) method: String,
+    /// the request path, without any query string.
+    pub(super) path: String,
+}
+
+/// a response to a request.
+pub(super) struct Response {
+    /// the status code and reason, such as `200 OK`.
+    pub(super) status: &'static str,
+    /// the type of the body.
+    pub(super) content_type: &'static str,
+    /// the body.
+    pub(super) body: String,
+}
+
+/// how long to wait for a client to send its request, or to accept the response, and how long
+/// to wait for a collector to accept a connection or respond.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// the longest request line and headers that will be read.
+const MAX_REQUEST: u64 = 8 * 1024;
+
+/// serves each connection to `listener` on a background thread, one at a time.
+pub(super) fn serve<F>(listener: TcpListener, handler: F) -> io::Result<()>
+where
+    F: Fn(&Request) -> Response + Send + 'static,
+{
+    std::thread::Builder::new()
+        .name("tach-http".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                // a misbehaving client should not stop the server.
+                let _ = stream.and_then(|stream| respond(stream, &handler));
+            }
+        })
+        .map(drop)
+}
+
+/// reads a request from `stream`, and writes the handler's response.
+fn respond(stream: TcpStream, handler: &impl Fn(&Request) -> Response) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    match read_request(&stream)? {
+        Some(request) => {
+            let head = request.method == "HEAD";
+            write_response(&stream, &handler(&request), !head)
+        }
+        None => write_response(
+            &stream,
+            &Response::text("400 Bad Request", "bad request\n"),
+            true,
+        ),
+    }
+}
+
+/// reads a request's line and headers, returning `None` if the request is malformed.
+fn read_request(stream: &TcpStream) -> io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST));
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Ok(None);
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Ok(None);
+    }
+    let request = Request {
+        method: method.to_owned(),
+        path: target.split('?').next().unwrap_or_default().to_owned(),
+    };
+
+    // skip the headers, until the blank line that ends them.
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        if header.trim_end().is_empty() {
+            return Ok(Some(request));
+        }
+    }
+}
+
+/// writes a response, and asks the client to close the connection.
+///
+/// the body is omitted if `body` is `false`, as in a response to a `HEAD` request.
+fn write_response(mut stream: &TcpStream, response: &Response, body: bool) -> io::Result<()> {
+    let Response {
+        status,
+        content_type,
+        body: content,
+    } = response;
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\n\
+         connection: close\r\n\r\n",
+        content.len(),
+    )?;
+    if body {
+        stream.write_all(content.as_bytes())?;
+    }
+    stream.flush()
+}
+
+/// sends `body` to `path` on `host` in a `POST` request, and waits for a successful response.
+pub(super) fn post(
+    host: &str,
+    port: u16,
+    path: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    let stream = connect(host, port)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    // ipv6 addresses are bracketed in the host header.
+    let authority = match host.contains(':') {
+        true => format!("[{host}]:{port}"),
+        false => format!("{host}:{port}"),
+    };
+    let mut writer = &stream;
+    write!(
+        writer,
+        "POST {path} HTTP/1.1\r\nhost: {authority}\r\ncontent-type: {content_type}\r\n\
+         content-length: {}\r\nconnection: close\r\n\r\n",
+        body.len(),
+    )?;
+    writer.write_all(body)?;
+    writer.flush()?;
+
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST)).read_line(&mut line)?;
+    match line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        Some(_) => Err(io::Error::other(format!(
+            "http://{authority}{path} responded with `{}`",
+            line.trim_end()
+        ))),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("http://{authority}{path} sent a malformed response"),
+        )),
+    }
+}
+
+/// connects to `host`, trying each of its addresses in turn.
+///
+/// each attempt is bounded by [`TIMEOUT`], so that an unreachable collector does not stall
+/// sampling until the system gives up on the connection.
+fn connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut last = None;
+    for address in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(error) => last = Some(error),
+        }
+    }
+
+    Err(last.unwrap_or_else(|| io::Error::other(format!("{host} has no addresses"))))
+}
+
+// === impl Response ===
+
+impl Response {
+    /// returns a plain text response.
+    pub(super) fn text(status: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into(),
+        }
+    }
+}
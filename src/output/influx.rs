@@ -0,0 +1,108 @@
+use {
+    super::{Emit, FIELDS},
+    crate::{sentinel::Recording, stat::Measurement},
+    std::{
+        io::{self, Write},
+        time::SystemTime,
+    },
+};
+
+/// writes each recording in the influxdb line protocol.
+///
+/// each measurement is written as a `tach_cpu` point, tagged with the host and with the cpu
+/// (`all` for the system as a whole). its fields are the percentage of time that was busy, the
+/// percentage of time spent in each state, and whether the counters were trustworthy. states that
+/// were not reported, and intervals in which no time passed, are omitted. timestamps are in
+/// nanoseconds since the unix epoch.
+///
+/// ```text
+/// tach_cpu,host=box,cpu=all busy=25,user=20,...,idle=75,trustworthy=true 1704110400000000000
+/// ```
+pub struct Influx<W> {
+    writer: W,
+    /// the value of the `host` tag, escaped.
+    host: String,
+}
+
+/// the name of the points that are written.
+const MEASUREMENT: &str = "tach_cpu";
+
+// === impl Influx ===
+
+impl<W: Write> Influx<W> {
+    /// returns a new influx emitter, writing to `writer`, tagged with this system's hostname.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            host: escape(&super::hostname()),
+        }
+    }
+
+    /// tags points with the given host, rather than this system's hostname.
+    pub fn with_host(self, host: &str) -> Self {
+        Self {
+            host: escape(host),
+            ..self
+        }
+    }
+
+    /// returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// writes a recording, stamped with `time`.
+    fn write(&mut self, recording: &Recording, time: SystemTime) -> io::Result<()> {
+        let timestamp = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        self.point("all", recording.system(), timestamp)?;
+        for (cpu, measurement) in recording.cpus() {
+            self.point(&cpu.get().to_string(), measurement, timestamp)?;
+        }
+
+        self.writer.flush()
+    }
+
+    /// writes a point for a single measurement.
+    fn point(&mut self, cpu: &str, measurement: &Measurement, timestamp: u128) -> io::Result<()> {
+        let Self { writer, host } = self;
+
+        write!(writer, "{MEASUREMENT},host={host},cpu={cpu} ")?;
+        for (name, state) in FIELDS {
+            if let Some(percentage) = super::percentage(measurement, state) {
+                write!(writer, "{name}={percentage},")?;
+            }
+        }
+        writeln!(
+            writer,
+            "trustworthy={} {timestamp}",
+            measurement.is_trustworthy()
+        )
+    }
+}
+
+impl<W: Write> Emit for Influx<W> {
+    fn emit(&mut self, recording: &Recording) -> io::Result<()> {
+        self.write(recording, SystemTime::now())
+    }
+}
+
+/// escapes a tag value, so that its commas, equals signs, and spaces are not mistaken for syntax.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    // an empty tag value is not allowed.
+    match escaped.is_empty() {
+        true => "unknown".to_owned(),
+        false => escaped,
+    }
+}
@@ -0,0 +1,174 @@
+//! json lines output.
+//!
+//! each recording is written as one json object, on its own line. the schema is versioned by the
+//! `schema` field, which is incremented when a field is removed or changes meaning. new fields
+//! may be added without changing the version, so parsers should ignore fields they do not know.
+//!
+//! # schema, version 1
+//!
+//! ```text
+//! {
+//!   "schema": 1,
+//!   "timestamp": "2024-01-01T12:00:00.000000Z",  // wall-clock time of the end, in utc
+//!   "interval": 1.0,                             // length of the recording, in seconds
+//!   "tick_rate": 100,                            // clock ticks per second
+//!   "system": <measurement>,                     // all cpus, in aggregate
+//!   "cpus": [{"cpu": 0, ...<measurement>}, ...], // cpus online at the start and the end
+//!   "appeared": [2],                             // cpus that came online
+//!   "disappeared": [3],                          // cpus that went offline
+//!   "rates": {                                   // per second, or null if not reported
+//!     "context_switches": 1234.5, "forks": 1.0, "interrupts": 100.0, "softirqs": 50.0,
+//!     "paged_in": 0.0, "paged_out": 0.0, "swapped_in": 0.0, "swapped_out": 0.0,
+//!     "procs_running": 2, "procs_blocked": 0
+//!   }
+//! }
+//! ```
+//!
+//! where each `<measurement>` is
+//!
+//! ```text
+//! {
+//!   "ratio": 0.25,           // active time over total time, or null if no time passed
+//!   "trustworthy": true,     // false if a counter went backwards
+//!   "ticks": {               // clock ticks spent in each state, or null if not reported
+//!     "user": 20, "nice": 0, "system": 5, "idle": 75, "iowait": 0,
+//!     "irq": 0, "softirq": 0, "steal": 0, "guest": 0, "guest_nice": 0
+//!   }
+//! }
+//! ```
+//!
+//! as in `/proc/stat`, `user` and `nice` ticks include `guest` and `guest_nice` ticks.
+
+use {
+    super::Emit,
+    crate::{
+        sentinel::Recording,
+        stat::{CpuId, Measurement, State},
+    },
+    std::{
+        collections::BTreeSet,
+        io::{self, Write},
+        time::SystemTime,
+    },
+};
+
+/// writes each recording as a json object, on its own line.
+///
+/// see the [module documentation](self) for the schema.
+pub struct Json<W> {
+    writer: W,
+}
+
+/// the version of the schema that is written.
+pub const SCHEMA: u32 = 1;
+
+// === impl Json ===
+
+impl<W: Write> Json<W> {
+    /// returns a new json emitter, writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// writes a recording, stamped with `time`.
+    fn write(&mut self, recording: &Recording, time: SystemTime) -> io::Result<()> {
+        let Self { writer } = self;
+        let rates = recording.rates();
+
+        write!(
+            writer,
+            r#"{{"schema":{SCHEMA},"timestamp":"{}","interval":{},"tick_rate":{},"system":"#,
+            super::rfc3339(time),
+            recording.elapsed().as_secs_f64(),
+            recording.tick_rate().get(),
+        )?;
+        Self::measurement(writer, recording.system())?;
+
+        write!(writer, r#","cpus":["#)?;
+        for (i, (cpu, measurement)) in recording.cpus().iter().enumerate() {
+            let comma = if i == 0 { "" } else { "," };
+            write!(writer, r#"{comma}{{"cpu":{},"#, cpu.get())?;
+            Self::fields(writer, measurement)?;
+            write!(writer, "}}")?;
+        }
+
+        write!(writer, r#"],"appeared":"#)?;
+        Self::ids(writer, recording.appeared())?;
+        write!(writer, r#","disappeared":"#)?;
+        Self::ids(writer, recording.disappeared())?;
+
+        let rate = |rate: Option<f64>| number(rate.filter(|rate| rate.is_finite()));
+        let count = |count: Option<u64>| number(count);
+        write!(
+            writer,
+            concat!(
+                r#","rates":{{"context_switches":{},"forks":{},"interrupts":{},"softirqs":{},"#,
+                r#""paged_in":{},"paged_out":{},"swapped_in":{},"swapped_out":{},"#,
+                r#""procs_running":{},"procs_blocked":{}}}}}"#,
+            ),
+            rate(rates.context_switches()),
+            rate(rates.forks()),
+            rate(rates.interrupts()),
+            rate(rates.softirqs()),
+            rate(rates.paged_in()),
+            rate(rates.paged_out()),
+            rate(rates.swapped_in()),
+            rate(rates.swapped_out()),
+            count(rates.procs_running()),
+            count(rates.procs_blocked()),
+        )?;
+
+        writeln!(writer)?;
+        writer.flush()
+    }
+
+    /// writes a measurement as a json object.
+    fn measurement(writer: &mut W, measurement: &Measurement) -> io::Result<()> {
+        write!(writer, "{{")?;
+        Self::fields(writer, measurement)?;
+        write!(writer, "}}")
+    }
+
+    /// writes the fields of a measurement, without the surrounding braces.
+    fn fields(writer: &mut W, measurement: &Measurement) -> io::Result<()> {
+        let ratio = measurement.ratio();
+        write!(
+            writer,
+            r#""ratio":{},"trustworthy":{},"ticks":{{"#,
+            number(ratio),
+            measurement.is_trustworthy(),
+        )?;
+        for (i, state) in State::ALL.into_iter().enumerate() {
+            let comma = if i == 0 { "" } else { "," };
+            let ticks = measurement.time(state).map(|ticks| ticks.get());
+            write!(writer, r#"{comma}"{state}":{}"#, number(ticks))?;
+        }
+        write!(writer, "}}")
+    }
+
+    /// writes a set of cpu ids as a json array.
+    fn ids(writer: &mut W, ids: &BTreeSet<CpuId>) -> io::Result<()> {
+        write!(writer, "[")?;
+        for (i, id) in ids.iter().enumerate() {
+            let comma = if i == 0 { "" } else { "," };
+            write!(writer, "{comma}{}", id.get())?;
+        }
+        write!(writer, "]")
+    }
+}
+
+impl<W: Write> Emit for Json<W> {
+    fn emit(&mut self, recording: &Recording) -> io::Result<()> {
+        self.write(recording, SystemTime::now())
+    }
+}
+
+/// formats a number as json, writing `null` if it is absent.
+fn number(n: Option<impl ToString>) -> String {
+    n.map_or_else(|| "null".to_owned(), |n| n.to_string())
+}
@@ -0,0 +1,431 @@
+//! opentelemetry metrics, pushed to a collector over otlp/http.
+//!
+//! each recording is exported as two metrics, following the semantic conventions for system
+//! metrics:
+//!
+//! - `system.cpu.time`, a cumulative monotonic sum of the seconds each cpu has spent in each
+//!   state since tach started.
+//! - `system.cpu.utilization`, a gauge of the fraction of the last interval that each cpu spent
+//!   in each state.
+//!
+//! each data point has a `cpu` attribute, the cpu's number, and a `state` attribute: one of
+//! `user`, `nice`, `system`, `idle`, `iowait`, `interrupt`, `softirq`, or `steal`. as in
+//! `/proc/stat`, `user` and `nice` time include the time spent running guests, so that the
+//! states of each cpu sum to its total time.
+//!
+//! the resource is described by `service.name` and `host.name` attributes.
+
+use {
+    super::{Dropped, Emit, http},
+    crate::{
+        cli::OtlpEncoding,
+        sentinel::Recording,
+        stat::{CpuId, Measurement, State},
+    },
+    std::{
+        collections::BTreeMap,
+        fmt::Write as _,
+        io,
+        time::{Duration, SystemTime},
+    },
+};
+
+/// pushes each recording to an opentelemetry collector, as otlp metrics over http.
+///
+/// see the [module documentation](self) for the metrics that are exported. a failed export is
+/// not an error, so that sampling continues while the collector is unavailable; since times are
+/// cumulative, the next successful export catches up. the first failure is reported on stderr, and
+/// the number of failures when the exporter is dropped; see [`Otlp::dropped()`].
+pub struct Otlp {
+    /// the collector's host.
+    host: String,
+    /// the collector's port.
+    port: u16,
+    /// the path that metrics are posted to.
+    path: String,
+    /// how requests are encoded.
+    encoding: OtlpEncoding,
+    /// the `host.name` resource attribute.
+    host_name: String,
+    /// when the cumulative times began, once the first recording is exported.
+    start: Option<SystemTime>,
+    /// how long each cpu has spent in each state, since the times began.
+    seconds: BTreeMap<(CpuId, State), Duration>,
+    /// the exports that failed.
+    dropped: Dropped,
+}
+
+/// a single data point, for one state of one cpu.
+struct Point {
+    cpu: CpuId,
+    state: &'static str,
+    value: f64,
+}
+
+/// the metrics exported for a recording.
+struct Export<'a> {
+    /// the `host.name` resource attribute.
+    host_name: &'a str,
+    /// when the cumulative times began, in nanoseconds since the unix epoch.
+    start: u64,
+    /// when the recording ended, in nanoseconds since the unix epoch.
+    time: u64,
+    /// the points of `system.cpu.time`.
+    seconds: Vec<Point>,
+    /// the points of `system.cpu.utilization`.
+    utilization: Vec<Point>,
+}
+
+/// the states that are exported, and their names in the semantic conventions.
+const STATES: [(State, &str); 8] = [
+    (State::User, "user"),
+    (State::Nice, "nice"),
+    (State::System, "system"),
+    (State::Idle, "idle"),
+    (State::Iowait, "iowait"),
+    (State::Irq, "interrupt"),
+    (State::Softirq, "softirq"),
+    (State::Steal, "steal"),
+];
+
+/// the description of `system.cpu.time`.
+const TIME: &str = "seconds each cpu has spent in each state.";
+
+/// the description of `system.cpu.utilization`.
+const UTILIZATION: &str = "fraction of the last interval that each cpu spent in each state.";
+
+/// the aggregation temporality of a cumulative sum.
+const CUMULATIVE: u64 = 2;
+
+// === impl Otlp ===
+
+impl Otlp {
+    /// returns a new otlp exporter, posting to `path` on the collector at `host` and `port`.
+    ///
+    /// requests are encoded as protobuf, and described by this system's hostname.
+    pub fn new(host: impl Into<String>, port: u16, path: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            path: path.into(),
+            encoding: OtlpEncoding::default(),
+            host_name: super::hostname(),
+            start: None,
+            seconds: BTreeMap::new(),
+            dropped: Dropped::new("otlp exports"),
+        }
+    }
+
+    /// encodes requests as `encoding`.
+    pub fn with_encoding(self, encoding: OtlpEncoding) -> Self {
+        Self { encoding, ..self }
+    }
+
+    /// describes the resource with the given host name, rather than this system's hostname.
+    pub fn with_host(self, host_name: impl Into<String>) -> Self {
+        Self {
+            host_name: host_name.into(),
+            ..self
+        }
+    }
+
+    /// returns the number of exports that failed.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.count()
+    }
+
+    /// adds a recording, which ended at `time`, to the cumulative times, and returns the
+    /// metrics to export.
+    fn record(&mut self, recording: &Recording, time: SystemTime) -> Export<'_> {
+        let Self {
+            host_name,
+            start,
+            seconds,
+            ..
+        } = self;
+        let start = *start.get_or_insert_with(|| time - recording.elapsed());
+
+        let mut utilization = Vec::new();
+        for (cpu, measurement) in recording.cpus() {
+            for (state, name) in STATES {
+                let Some(ticks) = measurement.time(state) else {
+                    continue;
+                };
+                *seconds.entry((*cpu, state)).or_default() += recording.duration(ticks);
+                if let Some(value) = Self::fraction(measurement, state) {
+                    let (cpu, state) = (*cpu, name);
+                    utilization.push(Point { cpu, state, value });
+                }
+            }
+        }
+
+        let name = |state: State| STATES.iter().find(|(s, _)| *s == state).map(|(_, n)| *n);
+        let seconds = seconds
+            .iter()
+            .filter_map(|(&(cpu, state), seconds)| {
+                let state = name(state)?;
+                let value = seconds.as_secs_f64();
+                Some(Point { cpu, state, value })
+            })
+            .collect();
+
+        Export {
+            host_name,
+            start: nanos(start),
+            time: nanos(time),
+            seconds,
+            utilization,
+        }
+    }
+
+    /// returns the fraction of a measurement's total time that was spent in `state`.
+    ///
+    /// unlike [`Measurement::fraction()`], `user` and `nice` time include guest time.
+    fn fraction(measurement: &Measurement, state: State) -> Option<f64> {
+        let total = measurement.total().get();
+        let ticks = measurement.time(state)?.get();
+        (total > 0).then(|| ticks as f64 / total as f64)
+    }
+}
+
+impl Emit for Otlp {
+    fn emit(&mut self, recording: &Recording) -> io::Result<()> {
+        let encoding = self.encoding;
+        let export = self.record(recording, SystemTime::now());
+        let (content_type, body) = match encoding {
+            OtlpEncoding::Protobuf => ("application/x-protobuf", export.protobuf()),
+            OtlpEncoding::Json => ("application/json", export.json().into_bytes()),
+        };
+
+        let Self {
+            host, port, path, ..
+        } = self;
+        if let Err(error) = http::post(host, *port, path, content_type, &body) {
+            self.dropped.record(&error);
+        }
+
+        Ok(())
+    }
+}
+
+// === impl Export ===
+
+impl Export<'_> {
+    /// encodes an `ExportMetricsServiceRequest` as protobuf.
+    fn protobuf(&self) -> Vec<u8> {
+        let Self {
+            host_name,
+            start,
+            time,
+            seconds,
+            utilization,
+        } = self;
+
+        let attribute = |key: &str, value: &str| {
+            let mut any = Protobuf::default();
+            any.string(1, value);
+            let mut attribute = Protobuf::default();
+            attribute.string(1, key).message(2, any);
+            attribute
+        };
+        let point = |point: &Point, start: Option<u64>| {
+            // the cpu is an integer, unlike the other attributes.
+            let mut number = Protobuf::default();
+            number.varint(3, point.cpu.get().into());
+            let mut cpu = Protobuf::default();
+            cpu.string(1, "cpu").message(2, number);
+
+            let mut message = Protobuf::default();
+            message
+                .message(7, cpu)
+                .message(7, attribute("state", point.state));
+            if let Some(start) = start {
+                message.fixed64(2, start);
+            }
+            message.fixed64(3, *time).double(4, point.value);
+            message
+        };
+
+        let mut sum = Protobuf::default();
+        for seconds in seconds {
+            sum.message(1, point(seconds, Some(*start)));
+        }
+        sum.varint(2, CUMULATIVE).varint(3, 1);
+        let mut time_metric = Protobuf::default();
+        time_metric
+            .string(1, "system.cpu.time")
+            .string(2, TIME)
+            .string(3, "s")
+            .message(7, sum);
+
+        let mut gauge = Protobuf::default();
+        for utilization in utilization {
+            gauge.message(1, point(utilization, None));
+        }
+        let mut utilization_metric = Protobuf::default();
+        utilization_metric
+            .string(1, "system.cpu.utilization")
+            .string(2, UTILIZATION)
+            .string(3, "1")
+            .message(5, gauge);
+
+        let mut scope = Protobuf::default();
+        scope.string(1, "tach").string(2, env!("CARGO_PKG_VERSION"));
+        let mut scope_metrics = Protobuf::default();
+        scope_metrics
+            .message(1, scope)
+            .message(2, time_metric)
+            .message(2, utilization_metric);
+
+        let mut resource = Protobuf::default();
+        resource
+            .message(1, attribute("service.name", "tach"))
+            .message(1, attribute("host.name", host_name));
+        let mut resource_metrics = Protobuf::default();
+        resource_metrics
+            .message(1, resource)
+            .message(2, scope_metrics);
+
+        let mut request = Protobuf::default();
+        request.message(1, resource_metrics);
+        request.0
+    }
+
+    /// encodes an `ExportMetricsServiceRequest` as json.
+    fn json(&self) -> String {
+        let Self {
+            host_name,
+            start,
+            time,
+            seconds,
+            utilization,
+        } = self;
+
+        let mut out = String::new();
+        let points = |out: &mut String, points: &[Point], start: Option<u64>| {
+            for (i, point) in points.iter().enumerate() {
+                let comma = if i == 0 { "" } else { "," };
+                let _ = write!(
+                    out,
+                    r#"{comma}{{"attributes":[{{"key":"cpu","value":{{"intValue":"{}"}}}},"#,
+                    point.cpu.get(),
+                );
+                let _ = write!(
+                    out,
+                    r#"{{"key":"state","value":{{"stringValue":{}}}}}],"#,
+                    super::json_string(point.state),
+                );
+                if let Some(start) = start {
+                    let _ = write!(out, r#""startTimeUnixNano":"{start}","#);
+                }
+                let _ = write!(
+                    out,
+                    r#""timeUnixNano":"{time}","asDouble":{}}}"#,
+                    point.value
+                );
+            }
+        };
+
+        let _ = write!(
+            out,
+            concat!(
+                r#"{{"resourceMetrics":[{{"resource":{{"attributes":["#,
+                r#"{{"key":"service.name","value":{{"stringValue":"tach"}}}},"#,
+                r#"{{"key":"host.name","value":{{"stringValue":{}}}}}]}},"#,
+                r#""scopeMetrics":[{{"scope":{{"name":"tach","version":"{}"}},"metrics":["#,
+                r#"{{"name":"system.cpu.time","description":{},"unit":"s","sum":{{"#,
+                r#""dataPoints":["#,
+            ),
+            super::json_string(host_name),
+            env!("CARGO_PKG_VERSION"),
+            super::json_string(TIME),
+        );
+        points(&mut out, seconds, Some(*start));
+        let _ = write!(
+            out,
+            concat!(
+                r#"],"aggregationTemporality":{},"isMonotonic":true}}}},"#,
+                r#"{{"name":"system.cpu.utilization","description":{},"unit":"1","gauge":{{"#,
+                r#""dataPoints":["#,
+            ),
+            CUMULATIVE,
+            super::json_string(UTILIZATION),
+        );
+        points(&mut out, utilization, None);
+        out.push_str("]}}]}]}]}");
+
+        out
+    }
+}
+
+/// a protobuf message, being encoded.
+#[derive(Default)]
+struct Protobuf(Vec<u8>);
+
+// === impl Protobuf ===
+
+impl Protobuf {
+    /// the wire type of integers, booleans, and enumerations.
+    const VARINT: u8 = 0;
+    /// the wire type of `fixed64` and `double` fields.
+    const I64: u8 = 1;
+    /// the wire type of strings and embedded messages.
+    const LEN: u8 = 2;
+
+    /// writes a field's number and wire type.
+    fn key(&mut self, field: u32, wire: u8) {
+        self.raw_varint(u64::from(field) << 3 | u64::from(wire));
+    }
+
+    /// writes an integer in base 128, least significant group first.
+    fn raw_varint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.0.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.0.push(n as u8);
+    }
+
+    /// writes an integer, boolean, or enumeration field.
+    fn varint(&mut self, field: u32, n: u64) -> &mut Self {
+        self.key(field, Self::VARINT);
+        self.raw_varint(n);
+        self
+    }
+
+    /// writes a `fixed64` field.
+    fn fixed64(&mut self, field: u32, n: u64) -> &mut Self {
+        self.key(field, Self::I64);
+        self.0.extend_from_slice(&n.to_le_bytes());
+        self
+    }
+
+    /// writes a `double` field.
+    fn double(&mut self, field: u32, n: f64) -> &mut Self {
+        self.fixed64(field, n.to_bits())
+    }
+
+    /// writes a string field.
+    fn string(&mut self, field: u32, s: &str) -> &mut Self {
+        self.bytes(field, s.as_bytes())
+    }
+
+    /// writes an embedded message field.
+    fn message(&mut self, field: u32, message: Protobuf) -> &mut Self {
+        self.bytes(field, &message.0)
+    }
+
+    /// writes a length-delimited field.
+    fn bytes(&mut self, field: u32, bytes: &[u8]) -> &mut Self {
+        self.key(field, Self::LEN);
+        self.raw_varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+        self
+    }
+}
+
+/// returns nanoseconds since the unix epoch, saturating if they do not fit.
+fn nanos(time: SystemTime) -> u64 {
+    let since = time.duration_since(SystemTime::UNIX_EPOCH);
+    u64::try_from(since.unwrap_or_default().as_nanos()).unwrap_or(u64::MAX)
+}
@@ -0,0 +1,222 @@
+use {
+    super::{
+        Emit,
+        http::{self, Request, Response},
+    },
+    crate::{
+        sentinel::Recording,
+        stat::{CpuId, State},
+    },
+    std::{
+        collections::BTreeMap,
+        fmt::Write as _,
+        io,
+        net::TcpListener,
+        sync::{Arc, Mutex, PoisonError},
+        time::{Duration, SystemTime},
+    },
+};
+
+/// serves the latest recording over http, in the prometheus text format.
+///
+/// each recording updates the metrics, which are served at `/metrics` once
+/// [`Prometheus::serve()`] is called.
+#[derive(Clone, Default)]
+pub struct Prometheus {
+    metrics: Arc<Mutex<Metrics>>,
+}
+
+/// the metrics, updated by each recording.
+#[derive(Default)]
+struct Metrics {
+    /// the number of recordings taken.
+    samples: u64,
+    /// the number of recordings in which a counter went backwards.
+    untrustworthy: u64,
+    /// how long each cpu has spent in each state, since tach started.
+    seconds: BTreeMap<(CpuId, State), Duration>,
+    /// the latest recording, and when it was taken.
+    latest: Option<(Recording, SystemTime)>,
+}
+
+/// the content type of the prometheus text format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// === impl Prometheus ===
+
+impl Prometheus {
+    /// returns a new prometheus exporter, with no recordings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// serves the metrics to connections to `listener`, on a background thread.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        let exporter = self.clone();
+        http::serve(listener, move |request| exporter.respond(request))
+    }
+
+    /// returns the metrics, in the prometheus text format.
+    pub fn render(&self) -> String {
+        self.metrics
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .render()
+    }
+
+    /// responds to a request for the metrics.
+    fn respond(&self, Request { method, path }: &Request) -> Response {
+        if !matches!(method.as_str(), "GET" | "HEAD") {
+            return Response::text("405 Method Not Allowed", "method not allowed\n");
+        }
+
+        match path.as_str() {
+            "/metrics" => Response {
+                status: "200 OK",
+                content_type: CONTENT_TYPE,
+                body: self.render(),
+            },
+            "/" => Response::text("200 OK", "tach\n\nmetrics are served at /metrics\n"),
+            _ => Response::text("404 Not Found", "not found\n"),
+        }
+    }
+}
+
+impl Emit for Prometheus {
+    fn emit(&mut self, recording: &Recording) -> io::Result<()> {
+        self.metrics
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record(recording, SystemTime::now());
+        Ok(())
+    }
+}
+
+// === impl Metrics ===
+
+impl Metrics {
+    /// updates the metrics with a recording, taken at `time`.
+    fn record(&mut self, recording: &Recording, time: SystemTime) {
+        let Self {
+            samples,
+            untrustworthy,
+            seconds,
+            latest,
+        } = self;
+
+        *samples += 1;
+        let trustworthy = std::iter::once(recording.system())
+            .chain(recording.cpus().values())
+            .all(|measurement| measurement.is_trustworthy());
+        if !trustworthy {
+            *untrustworthy += 1;
+        }
+
+        for (cpu, measurement) in recording.cpus() {
+            for state in State::ALL {
+                if let Some(ticks) = measurement.time(state) {
+                    *seconds.entry((*cpu, state)).or_default() += recording.duration(ticks);
+                }
+            }
+        }
+
+        *latest = Some((recording.clone(), time));
+    }
+
+    /// renders the metrics, in the prometheus text format.
+    fn render(&self) -> String {
+        let Self {
+            samples,
+            untrustworthy,
+            seconds,
+            latest,
+        } = self;
+
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, String)]| {
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{name}{labels} {value}");
+            }
+        };
+        let cpu = |cpu: &CpuId| format!(r#"{{cpu="{}"}}"#, cpu.get());
+
+        let recording = latest.as_ref().map(|(recording, _)| recording);
+        let system = recording
+            .and_then(|recording| recording.system().ratio())
+            .map(|ratio| (String::new(), ratio.to_string()));
+        metric(
+            "tach_system_utilization_ratio",
+            "gauge",
+            "fraction of time that all cpus were busy, in aggregate, during the last interval.",
+            &Vec::from_iter(system),
+        );
+
+        let cpus = recording.into_iter().flat_map(|recording| {
+            recording.cpus().iter().filter_map(|(id, measurement)| {
+                let ratio = measurement.ratio()?;
+                Some((cpu(id), ratio.to_string()))
+            })
+        });
+        metric(
+            "tach_cpu_utilization_ratio",
+            "gauge",
+            "fraction of time that each cpu was busy during the last interval.",
+            &Vec::from_iter(cpus),
+        );
+
+        let seconds = seconds.iter().map(|((id, state), seconds)| {
+            let labels = format!(r#"{{cpu="{}",mode="{state}"}}"#, id.get());
+            (labels, seconds.as_secs_f64().to_string())
+        });
+        metric(
+            "tach_cpu_seconds_total",
+            "counter",
+            "seconds each cpu spent in each mode since tach started. \
+             as in /proc/stat, user and nice time include guest time.",
+            &Vec::from_iter(seconds),
+        );
+
+        let online = recording.map(|recording| recording.cpus().len());
+        metric(
+            "tach_online_cpus",
+            "gauge",
+            "number of cpus that were online throughout the last interval.",
+            &Vec::from_iter(online.map(|n| (String::new(), n.to_string()))),
+        );
+
+        metric(
+            "tach_samples_total",
+            "counter",
+            "number of intervals that tach has measured.",
+            &[(String::new(), samples.to_string())],
+        );
+        metric(
+            "tach_untrustworthy_samples_total",
+            "counter",
+            "number of intervals in which a kernel counter went backwards.",
+            &[(String::new(), untrustworthy.to_string())],
+        );
+
+        let interval = recording.map(|recording| recording.elapsed().as_secs_f64());
+        metric(
+            "tach_sample_interval_seconds",
+            "gauge",
+            "length of the last interval.",
+            &Vec::from_iter(interval.map(|secs| (String::new(), secs.to_string()))),
+        );
+
+        let timestamp = latest.as_ref().map(|(_, time)| {
+            let since = time.duration_since(SystemTime::UNIX_EPOCH);
+            since.unwrap_or_default().as_secs_f64()
+        });
+        metric(
+            "tach_last_sample_timestamp_seconds",
+            "gauge",
+            "unix time at which the last interval ended.",
+            &Vec::from_iter(timestamp.map(|secs| (String::new(), secs.to_string()))),
+        );
+
+        out
+    }
+}
@@ -0,0 +1,153 @@
+//! writers for each [`Destination`].
+
+use {
+    super::{Dropped, http},
+    crate::cli::Destination,
+    std::{
+        fs::File,
+        io::{self, Write},
+        net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    },
+};
+
+/// opens a writer for `destination`.
+///
+/// emitters flush their writer after each recording. datagrams and requests are sent when the
+/// writer is flushed, so that each recording is sent as a whole. like the statsd and otlp
+/// emitters, these writers drop what cannot be sent rather than failing, so that sampling
+/// continues while the collector is missing.
+pub fn open(destination: &Destination) -> io::Result<Box<dyn Write>> {
+    match destination {
+        Destination::Stdout => Ok(Box::new(io::stdout())),
+        Destination::File(path) => Ok(Box::new(
+            File::options().create(true).append(true).open(path)?,
+        )),
+        Destination::Udp { host, port } => Ok(Box::new(Datagrams::new(udp(host, *port)?))),
+        Destination::Http { host, port, path } => Ok(Box::new(Post {
+            host: host.clone(),
+            port: *port,
+            path: path.clone(),
+            buffer: Vec::new(),
+            dropped: Dropped::new("requests"),
+        })),
+    }
+}
+
+/// returns a udp socket connected to `host`.
+pub(super) fn udp(host: &str, port: u16) -> io::Result<UdpSocket> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::other(format!("{host} has no addresses")))?;
+    let local = match address {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(address)?;
+    Ok(socket)
+}
+
+/// sends what is written as udp datagrams, when flushed.
+///
+/// datagrams are split between lines, so that none is longer than [`Datagrams::MAX`] unless a
+/// single line is.
+struct Datagrams {
+    socket: UdpSocket,
+    buffer: Vec<u8>,
+    /// the datagrams that could not be sent.
+    dropped: Dropped,
+}
+
+/// sends what is written in an http `POST` request, when flushed.
+struct Post {
+    host: String,
+    port: u16,
+    path: String,
+    buffer: Vec<u8>,
+    /// the requests that could not be sent.
+    dropped: Dropped,
+}
+
+// === impl Datagrams ===
+
+impl Datagrams {
+    /// the longest datagram that will be sent, if lines allow.
+    ///
+    /// this fits comfortably in the receive buffers of telegraf and statsd.
+    const MAX: usize = 8 * 1024;
+
+    /// returns a writer sending datagrams on a connected `socket`.
+    fn new(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            buffer: Vec::new(),
+            dropped: Dropped::new("datagrams"),
+        }
+    }
+}
+
+impl Write for Datagrams {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let Self {
+            socket,
+            buffer,
+            dropped,
+        } = self;
+
+        let mut rest = buffer.as_slice();
+        while !rest.is_empty() {
+            let end = match rest.len() <= Self::MAX {
+                true => rest.len(),
+                // the end of the last line that fits, or of the first line if none do.
+                false => rest[..Self::MAX]
+                    .iter()
+                    .rposition(|&b| b == b'\n')
+                    .or_else(|| rest.iter().position(|&b| b == b'\n'))
+                    .map_or(rest.len(), |newline| newline + 1),
+            };
+            if let Err(error) = socket.send(&rest[..end]) {
+                dropped.record(&error);
+            }
+            rest = &rest[end..];
+        }
+
+        buffer.clear();
+        Ok(())
+    }
+}
+
+// === impl Post ===
+
+impl Write for Post {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let body = std::mem::take(&mut self.buffer);
+        let sent = http::post(
+            &self.host,
+            self.port,
+            &self.path,
+            "text/plain; charset=utf-8",
+            &body,
+        );
+        if let Err(error) = sent {
+            self.dropped.record(&error);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use {
+    super::{Dropped, Emit, FIELDS},
+    crate::{sentinel::Recording, stat::Measurement},
+    std::{io, net::UdpSocket},
+};
+
+/// sends each recording to a statsd agent, as gauges over udp.
+///
+/// each measurement is sent as a gauge for the percentage of time that was busy, and for the
+/// percentage of time spent in each state. states that were not reported, and intervals in which
+/// no time passed, are not sent.
+///
+/// by default, each cpu's gauges are named for it, as in `tach.cpu.0.busy`, and the system as a
+/// whole is `tach.cpu.all.busy`. once [tagged](Statsd::with_tags), gauges are sent with
+/// dogstatsd-style tags instead, as in `tach.cpu.busy:25|g|#cpu:0`.
+///
+/// gauges are batched, several to a datagram. a failure to send is not an error, so that sampling
+/// continues while the agent is missing. the first failure is reported on stderr, and the number
+/// of failures when the emitter is dropped; see [`Statsd::dropped()`].
+pub struct Statsd {
+    socket: UdpSocket,
+    /// prepended to the name of each gauge, with a dot.
+    prefix: String,
+    /// tags for every gauge, or `None` to name each cpu's gauges for it instead.
+    tags: Option<Vec<String>>,
+    /// the datagrams that could not be sent.
+    dropped: Dropped,
+}
+
+// === impl Statsd ===
+
+impl Statsd {
+    /// the longest datagram that will be sent, unless a single gauge is longer.
+    ///
+    /// this keeps each datagram within a single ethernet frame, as statsd recommends.
+    const MAX_DATAGRAM: usize = 1432;
+
+    /// returns a new statsd emitter, sending to a connected `socket`.
+    pub fn new(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            prefix: "tach".to_owned(),
+            tags: None,
+            dropped: Dropped::new("statsd datagrams"),
+        }
+    }
+
+    /// returns a new statsd emitter, sending to the agent at `host` and `port`.
+    pub fn connect(host: &str, port: u16) -> io::Result<Self> {
+        super::sink::udp(host, port).map(Self::new)
+    }
+
+    /// prepends `prefix` to the name of each gauge, rather than `tach`.
+    ///
+    /// an empty prefix leaves names unprefixed.
+    pub fn with_prefix(self, prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            ..self
+        }
+    }
+
+    /// sends gauges with dogstatsd-style tags, including each of `tags`.
+    ///
+    /// each tag should be a `name:value` pair, or a bare name.
+    pub fn with_tags(self, tags: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            tags: Some(tags.into_iter().collect()),
+            ..self
+        }
+    }
+
+    /// returns the number of datagrams that could not be sent.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.count()
+    }
+
+    /// returns the gauges for a recording, one per line.
+    fn gauges(&self, recording: &Recording) -> Vec<String> {
+        let mut gauges = Vec::new();
+        self.measurement(&mut gauges, "all", recording.system());
+        for (cpu, measurement) in recording.cpus() {
+            self.measurement(&mut gauges, &cpu.get().to_string(), measurement);
+        }
+
+        gauges
+    }
+
+    /// adds the gauges for a single measurement.
+    fn measurement(&self, gauges: &mut Vec<String>, cpu: &str, measurement: &Measurement) {
+        let Self { prefix, tags, .. } = self;
+        let dot = if prefix.is_empty() { "" } else { "." };
+
+        for (field, state) in FIELDS {
+            let Some(percentage) = super::percentage(measurement, state) else {
+                continue;
+            };
+            gauges.push(match tags {
+                None => format!("{prefix}{dot}cpu.{cpu}.{field}:{percentage}|g"),
+                Some(tags) => {
+                    let mut gauge = format!("{prefix}{dot}cpu.{field}:{percentage}|g|#cpu:{cpu}");
+                    for tag in tags {
+                        gauge.push(',');
+                        gauge.push_str(tag);
+                    }
+                    gauge
+                }
+            });
+        }
+    }
+
+    /// joins gauges into datagrams, with a newline between each gauge.
+    fn batch(gauges: Vec<String>) -> Vec<String> {
+        let mut datagrams = Vec::<String>::new();
+        for gauge in gauges {
+            match datagrams.last_mut() {
+                Some(datagram) if datagram.len() + 1 + gauge.len() <= Self::MAX_DATAGRAM => {
+                    datagram.push('\n');
+                    datagram.push_str(&gauge);
+                }
+                _ => datagrams.push(gauge),
+            }
+        }
+
+        datagrams
+    }
+}
+
+impl Emit for Statsd {
+    fn emit(&mut self, recording: &Recording) -> io::Result<()> {
+        for datagram in Self::batch(self.gauges(recording)) {
+            if let Err(error) = self.socket.send(datagram.as_bytes()) {
+                self.dropped.record(&error);
+            }
+        }
+
+        Ok(())
+    }
+}
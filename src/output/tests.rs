@@ -0,0 +1,1237 @@
+use {
+    super::*,
+    crate::{
+        sentinel::Sentinel,
+        source::mock::{MockStatClock, MockStatFile},
+    },
+    std::time::{Duration, SystemTime},
+};
+
+/// returns a recording of the given pair of `/proc/stat` readings, one second apart.
+fn recording(a: &str, b: &str) -> Recording {
+    let source = MockStatFile::new([a, b]);
+    let clock = MockStatClock::ticking(Duration::from_secs(1), 2);
+    let mut sentinel = Sentinel::from_parts(clock, source);
+    assert!(sentinel.observe().unwrap().is_none());
+    sentinel.observe().unwrap().unwrap()
+}
+
+/// a request received by [`collector()`]: its line and headers, and its body.
+struct Received {
+    head: String,
+    body: Vec<u8>,
+}
+
+/// accepts `requests` http requests on a local port, responding with `status` to each, and
+/// returns the requests once they have all been received.
+fn collector(
+    status: &'static str,
+    requests: usize,
+) -> (u16, std::thread::JoinHandle<Vec<Received>>) {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let collector = std::thread::spawn(move || {
+        let mut received = Vec::new();
+        for _ in 0..requests {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut head = String::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.strip_prefix("content-length: ") {
+                    length = value.trim_end().parse().unwrap();
+                }
+                head.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            write!(stream, "HTTP/1.1 {status}\r\ncontent-length: 0\r\n\r\n").unwrap();
+            received.push(Received { head, body });
+        }
+        received
+    });
+
+    (port, collector)
+}
+
+mod text_tests {
+    use super::*;
+
+    fn text(recording: &Recording) -> String {
+        let mut text = Text::new(Vec::new());
+        text.emit(recording).unwrap();
+        String::from_utf8(text.into_inner()).unwrap()
+    }
+
+    /// splits a line into its columns, after the timestamp.
+    fn columns(line: &str) -> Vec<&str> {
+        line.split_whitespace().skip(1).collect()
+    }
+
+    #[test]
+    fn rows() {
+        let recording = recording(
+            "cpu 0 0 0 0 0 0 0 0 0 0\ncpu0 0 0 0 0 0 0 0 0 0 0\ncpu1 0 0 0 0 0 0 0 0 0 0\n",
+            "cpu 50 0 50 100 0 0 0 0 0 0\ncpu0 50 0 25 25 0 0 0 0 0 0\ncpu1 0 0 25 75 0 0 0 0 0 0\n",
+        );
+        let text = text(&recording);
+        let lines = text.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            columns(lines[0]),
+            [
+                "CPU", "%busy", "%usr", "%nice", "%sys", "%iowait", "%irq", "%soft", "%steal",
+                "%guest", "%gnice", "%idle"
+            ]
+        );
+        assert_eq!(
+            columns(lines[1]),
+            [
+                "all", "50.00", "25.00", "0.00", "25.00", "0.00", "0.00", "0.00", "0.00", "0.00",
+                "0.00", "50.00"
+            ]
+        );
+        assert_eq!(columns(lines[2])[..2], ["0", "75.00"]);
+        assert_eq!(columns(lines[3])[..2], ["1", "25.00"]);
+        assert_eq!(lines[4], "");
+    }
+
+    /// every row begins with a `HH:MM:SS` timestamp.
+    #[test]
+    fn timestamps() {
+        let recording = recording("cpu 0 0 0 0\n", "cpu 1 0 0 1\n");
+        for line in text(&recording).lines().filter(|line| !line.is_empty()) {
+            let time = line.split_whitespace().next().unwrap();
+            let parts = time.split(':').collect::<Vec<_>>();
+            assert_eq!(parts.len(), 3, "{line}");
+            assert!(parts.iter().all(|part| part.len() == 2), "{line}");
+        }
+    }
+
+    /// the header is repeated for each recording.
+    #[test]
+    fn repeated_header() {
+        let recording = recording("cpu 0 0 0 0\n", "cpu 1 0 0 1\n");
+        let mut text = Text::new(Vec::new());
+        text.emit(&recording).unwrap();
+        text.emit(&recording).unwrap();
+        let text = String::from_utf8(text.into_inner()).unwrap();
+        assert_eq!(text.matches("%busy").count(), 2);
+    }
+
+    /// states that older kernels do not report are shown as absent.
+    #[test]
+    fn unreported_states() {
+        let recording = recording("cpu 0 0 0 0\n", "cpu 1 0 0 1\n");
+        let text = text(&recording);
+        let all = columns(text.lines().nth(1).unwrap());
+        assert_eq!(all[1..5], ["50.00", "50.00", "0.00", "0.00"]);
+        assert_eq!(all[5..11], ["-", "-", "-", "-", "-", "-"]);
+        assert_eq!(all[11], "50.00");
+    }
+
+    /// an interval in which no time passed is shown as absent, rather than as `NaN`.
+    #[test]
+    fn zero_ticks() {
+        let recording = recording("cpu 1 0 0 1\n", "cpu 1 0 0 1\n");
+        let text = text(&recording);
+        assert!(!text.contains("NaN"));
+        assert_eq!(columns(text.lines().nth(1).unwrap())[1], "-");
+    }
+}
+
+mod rfc3339_tests {
+    use super::*;
+
+    fn at(secs: u64, micros: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_micros(micros)
+    }
+
+    #[test]
+    fn epoch() {
+        assert_eq!(rfc3339(at(0, 0)), "1970-01-01T00:00:00.000000Z");
+    }
+
+    #[test]
+    fn leap_day() {
+        assert_eq!(
+            rfc3339(at(1_709_210_096, 250)),
+            "2024-02-29T12:34:56.000250Z"
+        );
+    }
+
+    #[test]
+    fn end_of_year() {
+        assert_eq!(
+            rfc3339(at(1_704_067_199, 999_999)),
+            "2023-12-31T23:59:59.999999Z"
+        );
+    }
+}
+
+mod json_tests {
+    use super::*;
+
+    fn json(recording: &Recording) -> String {
+        let mut json = Json::new(Vec::new());
+        json.emit(recording).unwrap();
+        String::from_utf8(json.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn one_line_per_recording() {
+        let recording = recording("cpu 0 0 0 0\n", "cpu 1 0 0 1\n");
+        let mut json = Json::new(Vec::new());
+        json.emit(&recording).unwrap();
+        json.emit(&recording).unwrap();
+        let json = String::from_utf8(json.into_inner()).unwrap();
+        assert_eq!(json.lines().count(), 2);
+        assert!(json.lines().all(|line| line.starts_with(r#"{"schema":1,"#)));
+    }
+
+    #[test]
+    fn measurements() {
+        let recording = recording(
+            "cpu 0 0 0 0 0 0 0 0 0 0\ncpu0 0 0 0 0 0 0 0 0 0 0\n",
+            "cpu 20 0 5 75 0 0 0 0 0 0\ncpu0 20 0 5 75 0 0 0 0 0 0\n",
+        );
+        let json = json(&recording);
+        let measurement = concat!(
+            r#""ratio":0.25,"trustworthy":true,"ticks":{"user":20,"nice":0,"system":5,"#,
+            r#""idle":75,"iowait":0,"irq":0,"softirq":0,"steal":0,"guest":0,"guest_nice":0}"#,
+        );
+        assert!(
+            json.contains(&format!(r#""system":{{{measurement}}}"#)),
+            "{json}"
+        );
+        assert!(
+            json.contains(&format!(r#""cpus":[{{"cpu":0,{measurement}}}]"#)),
+            "{json}"
+        );
+        assert!(json.contains(r#""interval":1,"#), "{json}");
+    }
+
+    /// states and rates that were not reported, and undefined ratios, are `null`.
+    #[test]
+    fn nulls() {
+        let recording = recording("cpu 1 0 0 1\n", "cpu 1 0 0 1\n");
+        let json = json(&recording);
+        assert!(json.contains(r#""ratio":null"#), "{json}");
+        assert!(json.contains(r#""iowait":null"#), "{json}");
+        assert!(json.contains(r#""context_switches":null"#), "{json}");
+        assert!(!json.contains("NaN"), "{json}");
+    }
+
+    #[test]
+    fn hotplug() {
+        let recording = recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+            "cpu 1 0 0 1\ncpu0 1 0 0 1\ncpu2 0 0 0 0\n",
+        );
+        let json = json(&recording);
+        assert!(
+            json.contains(r#""appeared":[2],"disappeared":[1]"#),
+            "{json}"
+        );
+    }
+}
+
+mod csv_tests {
+    use {
+        super::*,
+        crate::{
+            cli::{CsvLayout, Destination},
+            stat::CpuId,
+        },
+    };
+
+    fn csv(csv: Csv<Vec<u8>>, recordings: &[Recording]) -> Vec<String> {
+        let mut csv = csv;
+        for recording in recordings {
+            csv.emit(recording).unwrap();
+        }
+        let csv = String::from_utf8(csv.into_inner()).unwrap();
+        csv.lines().map(str::to_owned).collect()
+    }
+
+    /// drops the timestamp from a row.
+    fn fields(row: &str) -> &str {
+        row.split_once(',').unwrap().1
+    }
+
+    #[test]
+    fn long() {
+        let recording = recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+            "cpu 1 0 0 3\ncpu0 1 0 0 1\ncpu1 0 0 0 2\n",
+        );
+        let rows = csv(Csv::new(Vec::new(), CsvLayout::Long), &[recording]);
+        assert_eq!(
+            rows[0],
+            "timestamp,interval,cpu,busy,user,nice,system,iowait,irq,softirq,steal,guest,\
+             guest_nice,idle,trustworthy"
+        );
+        assert_eq!(
+            fields(&rows[1]),
+            "1,all,25.000,25.000,0.000,0.000,,,,,,,75.000,true"
+        );
+        assert_eq!(
+            fields(&rows[2]),
+            "1,0,50.000,50.000,0.000,0.000,,,,,,,50.000,true"
+        );
+        assert_eq!(
+            fields(&rows[3]),
+            "1,1,0.000,0.000,0.000,0.000,,,,,,,100.000,true"
+        );
+        assert_eq!(rows.len(), 4);
+    }
+
+    /// the header is only written once.
+    #[test]
+    fn header_once() {
+        let a = recording("cpu 0 0 0 0\n", "cpu 1 0 0 1\n");
+        let b = a.clone();
+        for layout in [CsvLayout::Long, CsvLayout::Wide] {
+            let rows = csv(Csv::new(Vec::new(), layout), &[a.clone(), b.clone()]);
+            assert_eq!(rows.len(), 3);
+            assert_eq!(
+                rows.iter()
+                    .filter(|row| row.starts_with("timestamp"))
+                    .count(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn wide() {
+        let recording = recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+            "cpu 1 0 0 3\ncpu0 1 0 0 1\ncpu1 0 0 0 2\n",
+        );
+        let rows = csv(Csv::new(Vec::new(), CsvLayout::Wide), &[recording]);
+        let header = rows[0].split(',').collect::<Vec<_>>();
+        assert_eq!(header.len(), 2 + 3 * 11 + 1);
+        assert_eq!(header[2..4], ["all_busy", "all_user"]);
+        assert_eq!(header[13..15], ["cpu0_busy", "cpu0_user"]);
+        assert_eq!(header[24], "cpu1_busy");
+        assert_eq!(rows[1].split(',').count(), header.len());
+    }
+
+    /// cpus going offline and coming online do not change the columns.
+    #[test]
+    fn wide_hotplug() {
+        let online = recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+            "cpu 1 0 0 1\ncpu0 1 0 0 1\ncpu1 0 0 0 2\n",
+        );
+        let offline = recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+            "cpu 1 0 0 1\ncpu0 1 0 0 1\n",
+        );
+        let appeared = recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\n",
+            "cpu 1 0 0 1\ncpu0 1 0 0 1\ncpu2 0 0 0 0\n",
+        );
+
+        let csv = Csv::new(Vec::new(), CsvLayout::Wide).with_cpus([0, 1].map(CpuId::from));
+        let rows = self::csv(csv, &[online, offline, appeared]);
+        let columns = rows[0].split(',').count();
+        assert!(!rows[0].contains("cpu2"));
+        assert!(rows.iter().all(|row| row.split(',').count() == columns));
+
+        // the offline cpu's fields are empty.
+        let cpu1 = rows[2].split(',').skip(24).take(11).collect::<Vec<_>>();
+        assert!(cpu1.iter().all(|field| field.is_empty()));
+    }
+
+    /// appending to a file does not repeat the header, and keeps the file's columns.
+    #[test]
+    fn append() {
+        let path = std::env::temp_dir().join(format!("tach-csv-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let two_cpus = || {
+            recording(
+                "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+                "cpu 1 0 0 3\ncpu0 1 0 0 1\ncpu1 0 0 0 2\n",
+            )
+        };
+
+        for layout in [CsvLayout::Long, CsvLayout::Wide] {
+            // the second run knows of another cpu, but keeps the first run's columns.
+            for cpus in [[0, 1], [0, 2]] {
+                let mut csv = Csv::new(open(&Destination::File(path.clone())).unwrap(), layout)
+                    .with_cpus(cpus.map(CpuId::from))
+                    .resume(&path)
+                    .unwrap();
+                csv.emit(&two_cpus()).unwrap();
+            }
+
+            let contents = std::fs::read_to_string(&path).unwrap();
+            let rows = contents.lines().collect::<Vec<_>>();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(
+                rows.iter()
+                    .filter(|row| row.starts_with("timestamp"))
+                    .count(),
+                1
+            );
+            assert!(!rows[0].contains("cpu2"));
+
+            let columns = rows[0].split(',').count();
+            assert!(rows.iter().all(|row| row.split(',').count() == columns));
+        }
+    }
+
+    /// a file with another layout's header, or no csv at all, is not appended to.
+    #[test]
+    fn append_mismatch() {
+        let path = std::env::temp_dir().join(format!("tach-csv-mismatch-{}", std::process::id()));
+        let long = csv(
+            Csv::new(Vec::new(), CsvLayout::Long),
+            &[recording("cpu 0 0 0 0\n", "cpu 1 0 0 1\n")],
+        );
+        let resume = |contents: &str, layout| {
+            std::fs::write(&path, contents).unwrap();
+            Csv::new(Vec::new(), layout).resume(&path).map(drop)
+        };
+
+        assert!(resume(&long.join("\n"), CsvLayout::Long).is_ok());
+        assert!(resume(&long.join("\n"), CsvLayout::Wide).is_err());
+        assert!(resume("cpu 1 2 3 4\n", CsvLayout::Long).is_err());
+        assert!(resume("", CsvLayout::Wide).is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+mod prometheus_tests {
+    use {
+        super::*,
+        std::{
+            io::{Read, Write},
+            net::{TcpListener, TcpStream},
+        },
+    };
+
+    /// returns the value of the sample with the given name and labels.
+    fn sample<'a>(metrics: &'a str, series: &str) -> Option<&'a str> {
+        metrics
+            .lines()
+            .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+    }
+
+    #[test]
+    fn before_any_recording() {
+        let metrics = Prometheus::new().render();
+        assert_eq!(sample(&metrics, "tach_samples_total"), Some("0"));
+        assert_eq!(sample(&metrics, "tach_system_utilization_ratio"), None);
+        assert!(metrics.contains("# TYPE tach_cpu_seconds_total counter"));
+    }
+
+    #[test]
+    fn gauges() {
+        let recording = recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+            "cpu 100 0 0 300\ncpu0 100 0 0 100\ncpu1 0 0 0 200\n",
+        );
+        let mut exporter = Prometheus::new();
+        exporter.emit(&recording).unwrap();
+        let metrics = exporter.render();
+
+        assert_eq!(
+            sample(&metrics, "tach_system_utilization_ratio"),
+            Some("0.25")
+        );
+        assert_eq!(
+            sample(&metrics, r#"tach_cpu_utilization_ratio{cpu="0"}"#),
+            Some("0.5")
+        );
+        assert_eq!(
+            sample(&metrics, r#"tach_cpu_utilization_ratio{cpu="1"}"#),
+            Some("0")
+        );
+        assert_eq!(sample(&metrics, "tach_online_cpus"), Some("2"));
+        assert_eq!(sample(&metrics, "tach_sample_interval_seconds"), Some("1"));
+    }
+
+    /// cpu-seconds accumulate across recordings.
+    #[test]
+    fn counters() {
+        let recording = recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\n",
+            "cpu 0 0 0 0\ncpu0 50 0 0 50\n",
+        );
+        let mut exporter = Prometheus::new();
+        exporter.emit(&recording).unwrap();
+        exporter.emit(&recording).unwrap();
+        let metrics = exporter.render();
+
+        let user = sample(&metrics, r#"tach_cpu_seconds_total{cpu="0",mode="user"}"#)
+            .unwrap()
+            .parse::<f64>()
+            .unwrap();
+        let expected = 2.0 * recording.duration("50".parse().unwrap()).as_secs_f64();
+        assert_eq!(user, expected);
+        assert_eq!(sample(&metrics, "tach_samples_total"), Some("2"));
+        assert_eq!(
+            sample(&metrics, r#"tach_cpu_seconds_total{cpu="0",mode="iowait"}"#),
+            None
+        );
+    }
+
+    /// sends a raw http request to the exporter, returning the response.
+    fn request(exporter: &Prometheus, request: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        exporter.serve(listener).unwrap();
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn serve() {
+        let exporter = Prometheus::new();
+        let response = request(
+            &exporter,
+            "GET /metrics HTTP/1.1\r\nhost: localhost\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("content-type: text/plain; version=0.0.4"));
+        assert!(response.contains("tach_samples_total 0\n"), "{response}");
+    }
+
+    #[test]
+    fn serve_errors() {
+        let exporter = Prometheus::new();
+        let response = request(&exporter, "GET /nope HTTP/1.1\r\n\r\n");
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{response}"
+        );
+        let response = request(&exporter, "POST /metrics HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 "), "{response}");
+        let response = request(&exporter, "nonsense\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 "), "{response}");
+    }
+
+    #[test]
+    fn serve_head() {
+        let exporter = Prometheus::new();
+        let response = request(&exporter, "HEAD /metrics HTTP/1.1\r\n\r\n");
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(body.is_empty());
+    }
+}
+
+mod influx_tests {
+    use super::*;
+
+    fn influx(recording: &Recording) -> String {
+        let mut influx = Influx::new(Vec::new()).with_host("box");
+        influx.emit(recording).unwrap();
+        String::from_utf8(influx.into_inner()).unwrap()
+    }
+
+    /// splits a line into its measurement and tags, its fields, and its timestamp.
+    fn parts(line: &str) -> (&str, &str, u128) {
+        let mut parts = line.split(' ');
+        let (Some(key), Some(fields), Some(timestamp), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            panic!("malformed line: {line:?}");
+        };
+        (key, fields, timestamp.parse().unwrap())
+    }
+
+    #[test]
+    fn points() {
+        let recording = recording(
+            "cpu 0 0 0 0 0 0 0 0 0 0\ncpu0 0 0 0 0 0 0 0 0 0 0\ncpu1 0 0 0 0 0 0 0 0 0 0\n",
+            "cpu 50 0 50 100 0 0 0 0 0 0\ncpu0 50 0 25 25 0 0 0 0 0 0\ncpu1 0 0 25 75 0 0 0 0 0 0\n",
+        );
+        let influx = influx(&recording);
+        let lines = influx.lines().map(parts).collect::<Vec<_>>();
+
+        let keys = lines.iter().map(|(key, ..)| *key).collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            [
+                "tach_cpu,host=box,cpu=all",
+                "tach_cpu,host=box,cpu=0",
+                "tach_cpu,host=box,cpu=1",
+            ]
+        );
+        assert_eq!(
+            lines[0].1,
+            "busy=50,user=25,nice=0,system=25,iowait=0,irq=0,softirq=0,steal=0,guest=0,\
+             guest_nice=0,idle=50,trustworthy=true"
+        );
+        assert!(lines[1].1.starts_with("busy=75,user=50,"));
+        assert!(lines[2].1.starts_with("busy=25,user=0,"));
+    }
+
+    #[test]
+    fn timestamps() {
+        let recording = recording("cpu 0 0 0 0\n", "cpu 1 0 0 1\n");
+        let nanos = || {
+            let since = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH);
+            since.unwrap().as_nanos()
+        };
+
+        let before = nanos();
+        let influx = influx(&recording);
+        let after = nanos();
+
+        let (_, _, timestamp) = parts(influx.lines().next().unwrap());
+        assert!((before..=after).contains(&timestamp));
+    }
+
+    #[test]
+    fn unreported_states() {
+        let recording = recording("cpu 0 0 0 0\n", "cpu 25 0 0 75\n");
+        let influx = influx(&recording);
+        let (_, fields, _) = parts(influx.lines().next().unwrap());
+        assert_eq!(
+            fields,
+            "busy=25,user=25,nice=0,system=0,idle=75,trustworthy=true"
+        );
+    }
+
+    #[test]
+    fn zero_ticks() {
+        let recording = recording("cpu 1 0 0 1\n", "cpu 1 0 0 1\n");
+        let influx = influx(&recording);
+        let (_, fields, _) = parts(influx.lines().next().unwrap());
+        assert_eq!(fields, "trustworthy=true");
+    }
+
+    #[test]
+    fn escaped_host() {
+        let recording = recording("cpu 0 0 0 0\n", "cpu 1 0 0 1\n");
+        let mut influx = Influx::new(Vec::new()).with_host("my box,1=a");
+        influx.emit(&recording).unwrap();
+        let influx = String::from_utf8(influx.into_inner()).unwrap();
+        assert!(influx.starts_with(r"tach_cpu,host=my\ box\,1\=a,cpu=all "));
+    }
+}
+
+mod sink_tests {
+    use {
+        super::*,
+        crate::cli::Destination,
+        std::{io::Write, net::UdpSocket},
+    };
+
+    #[test]
+    fn file() {
+        let path = std::env::temp_dir().join(format!("tach-sink-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        for line in ["one\n", "two\n"] {
+            let mut writer = open(&Destination::File(path.clone())).unwrap();
+            writer.write_all(line.as_bytes()).unwrap();
+            writer.flush().unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contents, "one\ntwo\n");
+    }
+
+    #[test]
+    fn udp() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let destination = Destination::Udp {
+            host: "127.0.0.1".to_owned(),
+            port: socket.local_addr().unwrap().port(),
+        };
+        let mut writer = open(&destination).unwrap();
+        let receive = || {
+            let mut buffer = [0; 64 * 1024];
+            let len = socket.recv(&mut buffer).unwrap();
+            buffer[..len].to_vec()
+        };
+
+        // nothing is sent until the writer is flushed, and then it is sent together.
+        writer.write_all(b"one\n").unwrap();
+        writer.write_all(b"two\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(receive(), b"one\ntwo\n");
+
+        // long writes are split between lines.
+        let line = format!("{}\n", "x".repeat(5000));
+        writer.write_all(line.repeat(3).as_bytes()).unwrap();
+        writer.flush().unwrap();
+        for _ in 0..3 {
+            assert_eq!(receive(), line.as_bytes());
+        }
+    }
+
+    #[test]
+    fn http() {
+        let (port, collector) = collector("204 No Content", 1);
+        let destination = Destination::Http {
+            host: "127.0.0.1".to_owned(),
+            port,
+            path: "/write?db=tach".to_owned(),
+        };
+        let mut writer = open(&destination).unwrap();
+        writer.write_all(b"one\ntwo\n").unwrap();
+        writer.flush().unwrap();
+
+        let [Received { head, body }] = &collector.join().unwrap()[..] else {
+            panic!("expected one request");
+        };
+        assert!(head.starts_with("POST /write?db=tach HTTP/1.1\r\n"));
+        assert!(head.contains(&format!("host: 127.0.0.1:{port}\r\n")));
+        assert_eq!(body, b"one\ntwo\n");
+
+        // nothing is sent if nothing was written.
+        writer.flush().unwrap();
+    }
+
+    /// a request that fails is dropped, rather than sent again or ending the stream.
+    #[test]
+    fn http_error() {
+        let (port, collector) = collector("500 Internal Server Error", 2);
+        let destination = Destination::Http {
+            host: "127.0.0.1".to_owned(),
+            port,
+            path: "/".to_owned(),
+        };
+        let mut writer = open(&destination).unwrap();
+        for line in ["one\n", "two\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+            writer.flush().unwrap();
+        }
+
+        let bodies = collector
+            .join()
+            .unwrap()
+            .into_iter()
+            .map(|received| received.body)
+            .collect::<Vec<_>>();
+        assert_eq!(bodies, [b"one\n", b"two\n"]);
+    }
+
+    /// influx points are dropped while the collector is down or unreachable, without ending or
+    /// stalling the stream.
+    #[test]
+    fn collector_down() {
+        let recording = recording("cpu 0 0 0 0\n", "cpu 25 0 25 150\n");
+
+        // nothing is listening on ports that were just released.
+        let udp = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let destinations = [
+            Destination::Udp {
+                host: "127.0.0.1".to_owned(),
+                port: udp.port(),
+            },
+            Destination::Http {
+                host: "127.0.0.1".to_owned(),
+                port: tcp.port(),
+                path: "/write".to_owned(),
+            },
+            // an address reserved for documentation, which is never routed.
+            Destination::Http {
+                host: "192.0.2.1".to_owned(),
+                port: 8086,
+                path: "/write".to_owned(),
+            },
+        ];
+
+        for destination in destinations {
+            let mut influx = Influx::new(open(&destination).unwrap());
+            let start = std::time::Instant::now();
+            for _ in 0..3 {
+                influx.emit(&recording).unwrap();
+            }
+            // each request waits at most five seconds to connect.
+            assert!(start.elapsed() < Duration::from_secs(18));
+        }
+    }
+}
+
+mod statsd_tests {
+    use {super::*, std::net::UdpSocket};
+
+    /// returns an agent listening on a local port, and an emitter sending to it.
+    fn agent() -> (UdpSocket, Statsd) {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let port = agent.local_addr().unwrap().port();
+        (agent, Statsd::connect("127.0.0.1", port).unwrap())
+    }
+
+    /// emits a recording, and returns the datagrams that the agent received.
+    fn datagrams(agent: &UdpSocket, mut statsd: Statsd, recording: &Recording) -> Vec<String> {
+        statsd.emit(recording).unwrap();
+        assert_eq!(statsd.dropped(), 0);
+        agent.set_nonblocking(true).unwrap();
+        let mut datagrams = Vec::new();
+        let mut buffer = [0; 64 * 1024];
+        while let Ok(len) = agent.recv(&mut buffer) {
+            datagrams.push(String::from_utf8(buffer[..len].to_vec()).unwrap());
+        }
+        datagrams
+    }
+
+    fn two_cpus() -> Recording {
+        recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+            "cpu 25 0 25 150\ncpu0 25 0 0 75\ncpu1 0 0 25 75\n",
+        )
+    }
+
+    #[test]
+    fn named() {
+        let (agent, statsd) = agent();
+        let datagrams = datagrams(&agent, statsd, &two_cpus());
+        let gauges = datagrams.concat();
+        let gauges = gauges.lines().collect::<Vec<_>>();
+        assert_eq!(gauges.len(), 15);
+        assert_eq!(
+            gauges[..5],
+            [
+                "tach.cpu.all.busy:25|g",
+                "tach.cpu.all.user:12.5|g",
+                "tach.cpu.all.nice:0|g",
+                "tach.cpu.all.system:12.5|g",
+                "tach.cpu.all.idle:75|g",
+            ]
+        );
+        assert!(gauges.contains(&"tach.cpu.0.user:25|g"));
+        assert!(gauges.contains(&"tach.cpu.1.system:25|g"));
+    }
+
+    #[test]
+    fn tagged() {
+        let (agent, statsd) = agent();
+        let statsd = statsd
+            .with_prefix("box")
+            .with_tags(["env:prod".to_owned(), "canary".to_owned()]);
+        let gauges = datagrams(&agent, statsd, &two_cpus()).concat();
+        let gauges = gauges.lines().collect::<Vec<_>>();
+        assert_eq!(gauges[0], "box.cpu.busy:25|g|#cpu:all,env:prod,canary");
+        assert!(gauges.contains(&"box.cpu.user:25|g|#cpu:0,env:prod,canary"));
+    }
+
+    #[test]
+    fn unprefixed() {
+        let (agent, statsd) = agent();
+        let statsd = statsd.with_prefix("").with_tags([]);
+        let gauges = datagrams(&agent, statsd, &two_cpus()).concat();
+        assert!(gauges.starts_with("cpu.busy:25|g|#cpu:all\n"));
+    }
+
+    #[test]
+    fn batched() {
+        // enough cpus that their gauges cannot fit in one datagram.
+        let reading = |ticks: u64| {
+            let mut reading = format!("cpu {ticks} 0 0 {ticks}\n");
+            for cpu in 0..64 {
+                reading += &format!("cpu{cpu} {ticks} 0 0 {ticks}\n");
+            }
+            reading
+        };
+        let (agent, statsd) = agent();
+        let datagrams = datagrams(&agent, statsd, &recording(&reading(0), &reading(50)));
+
+        assert!(datagrams.len() > 1);
+        assert!(datagrams.iter().all(|datagram| datagram.len() <= 1432));
+        let gauges = datagrams.iter().flat_map(|datagram| datagram.lines());
+        assert_eq!(gauges.count(), 65 * 5);
+    }
+
+    #[test]
+    fn missing_agent() {
+        let (agent, mut statsd) = agent();
+        drop(agent);
+
+        let recording = two_cpus();
+        for _ in 0..3 {
+            assert!(statsd.emit(&recording).is_ok());
+        }
+        assert!(statsd.dropped() > 0);
+    }
+}
+
+mod otlp_tests {
+    use {super::*, crate::cli::OtlpEncoding};
+
+    /// a decoded protobuf field's value.
+    #[derive(Debug, PartialEq)]
+    enum Value<'a> {
+        Varint(u64),
+        I64(u64),
+        Len(&'a [u8]),
+    }
+
+    /// decodes the fields of a protobuf message.
+    fn fields(mut bytes: &[u8]) -> Vec<(u64, Value<'_>)> {
+        fn varint(bytes: &mut &[u8]) -> u64 {
+            let mut n = 0;
+            for shift in (0..).step_by(7) {
+                let (byte, rest) = bytes.split_first().unwrap();
+                *bytes = rest;
+                n |= u64::from(byte & 0x7f) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            n
+        }
+
+        let mut fields = Vec::new();
+        while !bytes.is_empty() {
+            let key = varint(&mut bytes);
+            let value = match key & 7 {
+                0 => Value::Varint(varint(&mut bytes)),
+                1 => {
+                    let (n, rest) = bytes.split_at(8);
+                    bytes = rest;
+                    Value::I64(u64::from_le_bytes(n.try_into().unwrap()))
+                }
+                2 => {
+                    let len = varint(&mut bytes) as usize;
+                    let (n, rest) = bytes.split_at(len);
+                    bytes = rest;
+                    Value::Len(n)
+                }
+                wire => panic!("unexpected wire type {wire}"),
+            };
+            fields.push((key >> 3, value));
+        }
+        fields
+    }
+
+    /// returns each length-delimited value of the given field.
+    fn messages(bytes: &[u8], field: u64) -> Vec<&[u8]> {
+        fields(bytes)
+            .into_iter()
+            .filter_map(|(number, value)| match value {
+                Value::Len(bytes) if number == field => Some(bytes),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// returns the single length-delimited value of the given field.
+    fn message(bytes: &[u8], field: u64) -> &[u8] {
+        let [message] = messages(bytes, field)[..] else {
+            panic!("expected one field {field}");
+        };
+        message
+    }
+
+    /// returns the value of the given fixed-width field.
+    fn fixed(bytes: &[u8], field: u64) -> Option<u64> {
+        fields(bytes)
+            .into_iter()
+            .find_map(|(number, value)| match value {
+                Value::I64(n) if number == field => Some(n),
+                _ => None,
+            })
+    }
+
+    /// returns a data point's `cpu` and `state` attributes, and its value.
+    fn point(bytes: &[u8]) -> (u64, &str, f64) {
+        let attributes = messages(bytes, 7);
+        let [cpu, state] = attributes[..] else {
+            panic!("expected two attributes");
+        };
+        assert_eq!(message(cpu, 1), b"cpu");
+        assert_eq!(message(state, 1), b"state");
+        let [(3, Value::Varint(cpu))] = fields(message(cpu, 2))[..] else {
+            panic!("expected an integer cpu");
+        };
+        let state = std::str::from_utf8(message(message(state, 2), 1)).unwrap();
+        (cpu, state, f64::from_bits(fixed(bytes, 4).unwrap()))
+    }
+
+    /// returns a sentinel's recordings of the given `/proc/stat` readings, one second apart.
+    fn recordings(readings: &[&str]) -> Vec<Recording> {
+        let source = MockStatFile::new(readings.iter().copied());
+        let clock = MockStatClock::ticking(Duration::from_secs(1), readings.len() as u32);
+        let mut sentinel = Sentinel::from_parts(clock, source);
+        let recordings = (0..readings.len()).filter_map(|_| sentinel.observe().unwrap());
+        recordings.collect()
+    }
+
+    /// emits each recording to a local collector, and returns the requests it received.
+    fn export(encoding: OtlpEncoding, recordings: &[Recording]) -> Vec<Received> {
+        let (port, collector) = collector("200 OK", recordings.len());
+        let mut otlp = Otlp::new("127.0.0.1", port, "/v1/metrics")
+            .with_encoding(encoding)
+            .with_host("box");
+        for recording in recordings {
+            otlp.emit(recording).unwrap();
+        }
+        assert_eq!(otlp.dropped(), 0);
+        collector.join().unwrap()
+    }
+
+    const READINGS: [&str; 3] = [
+        "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+        "cpu 75 0 25 100\ncpu0 50 0 0 50\ncpu1 25 0 25 50\n",
+        "cpu 175 0 25 200\ncpu0 100 0 0 100\ncpu1 75 0 25 100\n",
+    ];
+
+    #[test]
+    fn protobuf() {
+        let requests = export(OtlpEncoding::Protobuf, &recordings(&READINGS[..2]));
+        let [Received { head, body }] = &requests[..] else {
+            panic!("expected one request");
+        };
+        assert!(head.starts_with("POST /v1/metrics HTTP/1.1\r\n"));
+        assert!(head.contains("content-type: application/x-protobuf\r\n"));
+
+        let resource_metrics = message(body, 1);
+        let resource = message(resource_metrics, 1);
+        let attributes = messages(resource, 1);
+        assert_eq!(message(attributes[1], 1), b"host.name");
+        assert_eq!(message(message(attributes[1], 2), 1), b"box");
+
+        let scope_metrics = message(resource_metrics, 2);
+        assert_eq!(message(message(scope_metrics, 1), 1), b"tach");
+        let [time, utilization] = messages(scope_metrics, 2)[..] else {
+            panic!("expected two metrics");
+        };
+
+        assert_eq!(message(time, 1), b"system.cpu.time");
+        assert_eq!(message(time, 3), b"s");
+        let sum = message(time, 7);
+        let fields = fields(sum);
+        assert!(fields.contains(&(2, Value::Varint(2))));
+        assert!(fields.contains(&(3, Value::Varint(1))));
+        let points = messages(sum, 1);
+        // two cpus, with four states reported.
+        assert_eq!(points.len(), 8);
+        assert_eq!(point(points[0]), (0, "user", 0.5));
+        assert_eq!(point(points[6]), (1, "system", 0.25));
+        let (start, end) = (fixed(points[0], 2).unwrap(), fixed(points[0], 3).unwrap());
+        assert_eq!(end - start, 1_000_000_000);
+
+        assert_eq!(message(utilization, 1), b"system.cpu.utilization");
+        assert_eq!(message(utilization, 3), b"1");
+        let points = messages(message(utilization, 5), 1);
+        assert_eq!(points.len(), 8);
+        assert_eq!(point(points[3]), (0, "idle", 0.5));
+        assert_eq!(point(points[4]), (1, "user", 0.25));
+        assert_eq!(fixed(points[0], 2), None);
+    }
+
+    #[test]
+    fn cumulative() {
+        let requests = export(OtlpEncoding::Protobuf, &recordings(&READINGS));
+        let [first, second] = &requests[..] else {
+            panic!("expected two requests");
+        };
+
+        let metrics = |body| messages(message(message(body, 1), 2), 2);
+        let time = |body| messages(message(metrics(body)[0], 7), 1);
+        let utilization = |body| messages(message(metrics(body)[1], 5), 1);
+        let (first, second) = (&first.body[..], &second.body[..]);
+
+        // times accumulate, from the same start.
+        assert_eq!(point(time(first)[0]), (0, "user", 0.5));
+        assert_eq!(point(time(second)[0]), (0, "user", 1.0));
+        assert_eq!(fixed(time(first)[0], 2), fixed(time(second)[0], 2));
+        // utilization does not.
+        assert_eq!(point(utilization(second)[0]), (0, "user", 0.5));
+    }
+
+    #[test]
+    fn json() {
+        let requests = export(OtlpEncoding::Json, &recordings(&READINGS[..2]));
+        let [Received { head, body }] = &requests[..] else {
+            panic!("expected one request");
+        };
+        assert!(head.contains("content-type: application/json\r\n"));
+
+        let body = std::str::from_utf8(body).unwrap();
+        assert!(body.starts_with(concat!(
+            r#"{"resourceMetrics":[{"resource":{"attributes":["#,
+            r#"{"key":"service.name","value":{"stringValue":"tach"}},"#,
+            r#"{"key":"host.name","value":{"stringValue":"box"}}]},"#,
+        )));
+        assert!(body.contains(r#""name":"system.cpu.time","#));
+        assert!(body.contains(r#""aggregationTemporality":2,"isMonotonic":true"#));
+        assert!(body.contains(r#""name":"system.cpu.utilization","#));
+        assert!(body.contains(concat!(
+            r#"{"attributes":[{"key":"cpu","value":{"intValue":"1"}},"#,
+            r#"{"key":"state","value":{"stringValue":"system"}}],"startTimeUnixNano":""#,
+        )));
+        assert!(body.ends_with(r#""asDouble":0.5}]}}]}]}]}"#));
+        assert_eq!(body.matches('{').count(), body.matches('}').count());
+    }
+
+    #[test]
+    fn missing_collector() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut otlp = Otlp::new("127.0.0.1", port, "/v1/metrics");
+        for recording in recordings(&READINGS) {
+            assert!(otlp.emit(&recording).is_ok());
+        }
+        assert_eq!(otlp.dropped(), 2);
+    }
+
+    /// a collector that cannot be reached is given up on, rather than stalling sampling.
+    #[test]
+    fn unreachable_collector() {
+        // an address reserved for documentation, which is never routed.
+        let mut otlp = Otlp::new("192.0.2.1", 4318, "/v1/metrics");
+        let start = std::time::Instant::now();
+        for recording in recordings(&READINGS) {
+            assert!(otlp.emit(&recording).is_ok());
+        }
+        // each export waits at most five seconds to connect.
+        assert!(start.elapsed() < Duration::from_secs(12));
+        assert_eq!(otlp.dropped(), 2);
+    }
+
+    #[test]
+    fn offline_cpu() {
+        let recordings = recordings(&[
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+            "cpu 50 0 0 50\ncpu0 50 0 0 50\n",
+        ]);
+        let requests = export(OtlpEncoding::Protobuf, &recordings);
+        let scope_metrics = message(message(&requests[0].body, 1), 2);
+        let points = messages(message(messages(scope_metrics, 2)[0], 7), 1);
+        // cpu 1 went offline, so only cpu 0 is reported.
+        assert_eq!(points.len(), 4);
+        assert!(points.iter().all(|bytes| point(bytes).0 == 0));
+    }
+}
+
+mod bar_tests {
+    use {super::*, crate::meter::Align};
+
+    fn bar(bar: Bar<Vec<u8>>, recordings: &[Recording]) -> Vec<String> {
+        let mut bar = bar;
+        for recording in recordings {
+            bar.emit(recording).unwrap();
+        }
+        let out = String::from_utf8(bar.into_inner()).unwrap();
+        out.lines().map(str::to_owned).collect()
+    }
+
+    /// a system with two cpus, busy for a quarter and three quarters of a second.
+    fn two_cpus() -> Recording {
+        recording(
+            "cpu 0 0 0 0\ncpu0 0 0 0 0\ncpu1 0 0 0 0\n",
+            "cpu 100 0 0 100\ncpu0 25 0 0 75\ncpu1 75 0 0 25\n",
+        )
+    }
+
+    #[test]
+    fn tmux() {
+        let lines = bar(
+            Bar::new(Vec::new(), BarProtocol::Tmux),
+            &[two_cpus(), recording("cpu 0 0 0 0\n", "cpu 9 0 0 1\n")],
+        );
+        assert_eq!(
+            lines,
+            [
+                "#[fg=yellow]⣿⣿⣿⣿⠀⠀⠀⠀  50%#[default]",
+                "#[fg=red]⣿⣿⣿⣿⣿⣿⣿⣀  90%#[default]",
+            ]
+        );
+    }
+
+    #[test]
+    fn meter() {
+        let lines = bar(
+            Bar::new(Vec::new(), BarProtocol::Tmux).with_meter(4, Align::Right),
+            &[recording("cpu 0 0 0 0\n", "cpu 1 0 0 3\n")],
+        );
+        assert_eq!(lines, ["#[fg=green]⠀⠀⠀⣿  25%#[default]"]);
+    }
+
+    #[test]
+    fn cpus() {
+        let lines = bar(
+            Bar::new(Vec::new(), BarProtocol::Tmux).with_scope(BarScope::Cpus),
+            &[two_cpus()],
+        );
+        // a column of dots for each cpu, two to a cell.
+        assert_eq!(lines, ["#[fg=yellow]⣰  50%#[default]"]);
+    }
+
+    #[test]
+    fn i3bar() {
+        let lines = bar(
+            Bar::new(Vec::new(), BarProtocol::I3bar),
+            &[two_cpus(), two_cpus()],
+        );
+        let block = concat!(
+            r#"[{"name":"tach","full_text":"⣿⣿⣿⣿⠀⠀⠀⠀  50%","short_text":"50%","#,
+            r##""color":"#FFFF00","markup":"none"}]"##,
+        );
+        assert_eq!(
+            lines,
+            [
+                r#"{"version":1}"#.to_owned(),
+                "[".to_owned(),
+                block.to_owned(),
+                format!(",{block}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn waybar() {
+        let lines = bar(Bar::new(Vec::new(), BarProtocol::Waybar), &[two_cpus()]);
+        assert_eq!(
+            lines,
+            [concat!(
+                r#"{"text":"⣿⣿⣿⣿⠀⠀⠀⠀  50%","#,
+                r#""tooltip":"cpu   50%\ncpu0  25%\ncpu1  75%","#,
+                r#""class":"warning","percentage":50}"#,
+            )]
+        );
+    }
+
+    #[test]
+    fn zero_ticks() {
+        let lines = bar(
+            Bar::new(Vec::new(), BarProtocol::Waybar),
+            &[recording("cpu 1 0 0 1\n", "cpu 1 0 0 1\n")],
+        );
+        assert!(lines[0].contains(r#""class":"normal","percentage":0"#));
+    }
+}
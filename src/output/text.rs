@@ -0,0 +1,117 @@
+use {
+    super::Emit,
+    crate::{
+        sentinel::Recording,
+        stat::{Measurement, State},
+    },
+    std::{
+        io::{self, Write},
+        time::SystemTime,
+    },
+};
+
+/// writes each recording as rows of plain text, in the style of `mpstat -P ALL`.
+///
+/// each recording is written as a header, a row for the whole system, and a row for each cpu.
+/// every row begins with the local time at which it was written.
+pub struct Text<W> {
+    writer: W,
+}
+
+/// the columns following the cpu, and the states that they show.
+const COLUMNS: [(&str, Option<State>); 11] = [
+    ("%busy", None),
+    ("%usr", Some(State::User)),
+    ("%nice", Some(State::Nice)),
+    ("%sys", Some(State::System)),
+    ("%iowait", Some(State::Iowait)),
+    ("%irq", Some(State::Irq)),
+    ("%soft", Some(State::Softirq)),
+    ("%steal", Some(State::Steal)),
+    ("%guest", Some(State::Guest)),
+    ("%gnice", Some(State::GuestNice)),
+    ("%idle", Some(State::Idle)),
+];
+
+// === impl Text ===
+
+impl<W: Write> Text<W> {
+    /// returns a new text emitter, writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// writes a recording, with each row stamped with `time`.
+    fn write(&mut self, recording: &Recording, time: &str) -> io::Result<()> {
+        let Self { writer } = self;
+
+        write!(writer, "{time}  {:>4}", "CPU")?;
+        for (name, _) in COLUMNS {
+            write!(writer, " {name:>7}")?;
+        }
+        writeln!(writer)?;
+
+        Self::row(writer, time, "all", recording.system())?;
+        for (cpu, measurement) in recording.cpus() {
+            Self::row(writer, time, &cpu.get().to_string(), measurement)?;
+        }
+
+        // a blank line separates each recording, as `mpstat` does.
+        writeln!(writer)?;
+        writer.flush()
+    }
+
+    /// writes a row showing how `cpu` spent its time.
+    ///
+    /// states that were not reported, and intervals in which no time passed, are shown as `-`.
+    fn row(writer: &mut W, time: &str, cpu: &str, measurement: &Measurement) -> io::Result<()> {
+        write!(writer, "{time}  {cpu:>4}")?;
+        for (_, state) in COLUMNS {
+            let fraction = match state {
+                Some(state) => measurement.fraction(state),
+                None => measurement.ratio(),
+            };
+            match fraction {
+                Some(fraction) => write!(writer, " {:>7.2}", fraction * 100.0)?,
+                None => write!(writer, " {:>7}", "-")?,
+            }
+        }
+        writeln!(writer)
+    }
+}
+
+impl<W: Write> Emit for Text<W> {
+    fn emit(&mut self, recording: &Recording) -> io::Result<()> {
+        self.write(recording, &local_time(SystemTime::now()))
+    }
+}
+
+/// formats `time` as `HH:MM:SS`, in the local time zone.
+///
+/// times that cannot be converted are formatted in utc.
+fn local_time(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let utc = || {
+        let secs = secs % (24 * 60 * 60);
+        format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    };
+
+    let Ok(t) = libc::time_t::try_from(secs) else {
+        return utc();
+    };
+    // SAFETY: `tm` is plain data, so all zeroes is a valid value. `localtime_r` only writes to
+    // the `tm` it is given, and returns null if the time cannot be converted.
+    let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+    if unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+        return utc();
+    }
+
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
@@ -1,137 +1,243 @@
 use {
     crate::{
         source::{Clock, ProcStatFile, StatsSource, SystemClock},
-        stat::{CpuId, Measurement, Snapshot, StatReadError},
+        stat::{
+            Accounting, CpuId, CpuTime, Measurement, Rates, Snapshot, StatReadError, TickRate,
+            UserHz,
+        },
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        time::{Duration, Instant},
     },
-    std::{collections::BTreeMap, time::Instant},
 };
 
+#[cfg(test)]
+mod tests;
+
 /// observes kernel statistics.
+///
+/// each call to [`Sentinel::observe()`] reads a [`Snapshot`] from the source of statistics, and
+/// compares it to the previous one.
 pub struct Sentinel<C = SystemClock, S = ProcStatFile> {
-    inner: Inner<C, S>,
-}
-
-enum Inner<C, S> {
-    Initialized {
-        /// the clock being used to measure time.
-        clock: C,
-        /// the underlying source of kernel statistics.
-        source: S,
-    },
-    Running {
-        /// the clock being used to measure time.
-        clock: C,
-        /// the underlying source of kernel statistics.
-        source: S,
-        /// the last observed snapshot.
-        last: Snapshot,
-    },
+    /// the clock being used to measure time.
+    clock: C,
+    /// the underlying source of kernel statistics.
+    source: S,
+    /// the number of clock ticks in a second.
+    tick_rate: TickRate,
+    /// decides which cpu states count as busy.
+    accounting: Accounting,
+    /// the last observed snapshot, if one has been taken.
+    last: Option<Snapshot>,
 }
 
 /// a recording of the system's cpu load.
 #[derive(Clone, Debug)]
 pub struct Recording {
     /// when the recording began.
-    #[allow(dead_code)]
-    pub start: Instant,
+    start: Instant,
     /// when the recording ended.
-    #[allow(dead_code)]
-    pub end: Instant,
+    end: Instant,
     /// how the system cpus spent their time, in aggregate.
-    #[allow(dead_code)]
-    pub system: Measurement,
+    system: Measurement,
     /// how each cpu spent its time.
-    pub cpus: BTreeMap<CpuId, Measurement>,
+    ///
+    /// this only includes cpus that were online at the start and the end of the recording.
+    cpus: BTreeMap<CpuId, Measurement>,
+    /// cpus that came online during the recording.
+    appeared: BTreeSet<CpuId>,
+    /// cpus that went offline during the recording.
+    disappeared: BTreeSet<CpuId>,
+    /// the rates of system-wide activity, such as context switches and forks.
+    rates: Rates,
+    /// the number of clock ticks in a second.
+    tick_rate: TickRate,
 }
 
-/// === impl Sentinel ===
+// === impl Sentinel ===
 
-impl<S: Default, C: Default> Sentinel<C, S> {
+impl<C: Default, S: Default> Default for Sentinel<C, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Default, S: Default> Sentinel<C, S> {
     /// creates a new [`Sentinel`].
+    ///
+    /// the system's tick rate is queried when this is called.
     pub fn new() -> Self {
+        Self::from_parts(C::default(), S::default())
+    }
+}
+
+impl<C, S> Sentinel<C, S> {
+    /// creates a new [`Sentinel`] from the given clock and source of statistics.
+    ///
+    /// the system's tick rate is queried when this is called.
+    pub fn from_parts(clock: C, source: S) -> Self {
         Self {
-            inner: Inner::Initialized {
-                clock: C::default(),
-                source: S::default(),
-            },
+            clock,
+            source,
+            tick_rate: TickRate::query(),
+            accounting: Accounting::default(),
+            last: None,
         }
     }
+
+    /// overrides the tick rate used to interpret statistics.
+    ///
+    /// this is useful when statistics were recorded on another system.
+    pub fn with_tick_rate(self, tick_rate: TickRate) -> Self {
+        Self { tick_rate, ..self }
+    }
+
+    /// decides which cpu states count as busy using `accounting`.
+    pub fn with_accounting(self, accounting: Accounting) -> Self {
+        Self { accounting, ..self }
+    }
 }
 
-impl<S, C> Sentinel<C, S>
+impl<C, S> Sentinel<C, S>
 where
-    S: StatsSource + Default,
-    C: Clock + Default,
+    C: Clock,
+    S: StatsSource,
 {
     /// returns a [`Recording`] of cpu time since this was last called.
     ///
     /// NB: by virtue of this being a comparison to the previous reading, this will return
     /// `Ok(None)` the first time it is called.
+    ///
+    /// if the clock has not advanced since the previous reading, this returns
+    /// [`StatReadError::NonMonotonic`], and the next recording begins at this reading instead.
     pub fn observe(&mut self) -> Result<Option<Recording>, StatReadError> {
-        let Self { inner } = self;
-
-        match inner {
-            Inner::Initialized { clock, source } => {
-                let clock = std::mem::take(clock);
-                let source = std::mem::take(source);
-                let last = Snapshot::read(&source, &clock)?;
-                *inner = Inner::Running {
-                    clock,
-                    source,
-                    last,
-                };
-                Ok(None)
-            }
-            Inner::Running {
-                clock,
-                source: stats,
-                last,
-            } => {
-                let new = Snapshot::read(stats, clock)?;
-                let prev = std::mem::replace(last, new.clone());
-                let recording = Recording::new(prev, new);
-                Ok(Some(recording))
-            }
+        let Self {
+            clock,
+            source,
+            tick_rate,
+            accounting,
+            last,
+        } = self;
+
+        let new = Snapshot::read(source, clock)?;
+        let Some(prev) = last.replace(new.clone()) else {
+            return Ok(None);
+        };
+        if new.time() <= prev.time() {
+            return Err(StatReadError::NonMonotonic);
         }
+
+        Ok(Some(Recording::new(&prev, &new, *tick_rate, *accounting)))
     }
 }
 
 // === impl Recording ===
 
 impl Recording {
-    fn new(
-        Snapshot {
-            system: system_a,
-            cpus: cpus_a,
-            time: time_a,
-        }: Snapshot,
-        Snapshot {
-            system: system_b,
-            cpus: cpus_b,
-            time: time_b,
-        }: Snapshot,
-    ) -> Recording {
-        assert!(cpus_a.len() == cpus_b.len());
-        assert!(time_b > time_a);
-
-        let system = Measurement::new(system_a, system_b);
-
-        // zip together the two sets of cpu times.
-        let mut diff = BTreeMap::new();
-        let (mut a_iter, mut b_iter) = (cpus_a.into_iter(), cpus_b.into_iter());
-        while let Some((id_a, times_a)) = a_iter.next() {
-            let (id_b, times_b) = b_iter.next().unwrap();
-            assert!(id_a == id_b);
-            let times = Measurement::new(times_a, times_b);
-            diff.insert(id_a, times);
-        }
-        assert!(b_iter.next().is_none());
+    fn new(a: &Snapshot, b: &Snapshot, tick_rate: TickRate, accounting: Accounting) -> Recording {
+        let measure = |a: &CpuTime, b: &CpuTime| {
+            Measurement::new(a.clone(), b.clone()).with_accounting(accounting)
+        };
+        let system = measure(a.system(), b.system());
+        let rates = Rates::new(
+            a.counters().clone(),
+            b.counters().clone(),
+            b.time() - a.time(),
+        );
+        let (cpus_a, cpus_b) = (a.cpus(), b.cpus());
+
+        // cpus may have been brought online or taken offline between the two snapshots.
+        let appeared = cpus_b
+            .keys()
+            .filter(|id| !cpus_a.contains_key(id))
+            .copied()
+            .collect();
+        let disappeared = cpus_a
+            .keys()
+            .filter(|id| !cpus_b.contains_key(id))
+            .copied()
+            .collect();
+
+        // zip together the cpu times of cpus that were online in both snapshots.
+        let diff = cpus_a
+            .iter()
+            .filter_map(|(id, times_a)| Some((*id, measure(times_a, cpus_b.get(id)?))))
+            .collect();
 
         Self {
-            start: time_a,
-            end: time_b,
+            start: a.time(),
+            end: b.time(),
             system,
             cpus: diff,
+            appeared,
+            disappeared,
+            rates,
+            tick_rate,
         }
     }
+
+    /// returns when the recording began.
+    pub fn start(&self) -> Instant {
+        self.start
+    }
+
+    /// returns when the recording ended.
+    pub fn end(&self) -> Instant {
+        self.end
+    }
+
+    /// returns how the system cpus spent their time, in aggregate.
+    pub fn system(&self) -> &Measurement {
+        &self.system
+    }
+
+    /// returns how each cpu spent its time.
+    ///
+    /// this only includes cpus that were online at the start and the end of the recording.
+    pub fn cpus(&self) -> &BTreeMap<CpuId, Measurement> {
+        &self.cpus
+    }
+
+    /// returns the cpus that came online during the recording.
+    pub fn appeared(&self) -> &BTreeSet<CpuId> {
+        &self.appeared
+    }
+
+    /// returns the cpus that went offline during the recording.
+    pub fn disappeared(&self) -> &BTreeSet<CpuId> {
+        &self.disappeared
+    }
+
+    /// returns the rates of system-wide activity, such as context switches and forks.
+    pub fn rates(&self) -> &Rates {
+        &self.rates
+    }
+
+    /// returns the number of clock ticks in a second.
+    pub fn tick_rate(&self) -> TickRate {
+        self.tick_rate
+    }
+
+    /// returns the wall-clock length of this recording.
+    pub fn elapsed(&self) -> Duration {
+        self.end - self.start
+    }
+
+    /// returns the amount of time that the given number of clock ticks represents.
+    pub fn duration(&self, ticks: UserHz) -> Duration {
+        ticks.as_duration(self.tick_rate)
+    }
+
+    /// returns the average number of cpus that were busy, across the whole system.
+    pub fn cores_busy(&self) -> Option<f64> {
+        self.system.cores_busy(self.tick_rate, self.elapsed())
+    }
+
+    /// keeps only the cpus for which `f` returns `true`.
+    pub fn retain_cpus(&mut self, mut f: impl FnMut(&CpuId) -> bool) {
+        self.cpus.retain(|id, _| f(id));
+        self.appeared.retain(&mut f);
+        self.disappeared.retain(&mut f);
+    }
 }
@@ -0,0 +1,116 @@
+use {
+    super::*,
+    crate::source::mock::{MockStatClock, MockStatFile},
+    std::time::Duration,
+};
+
+mod hotplug_tests {
+    use super::*;
+
+    /// returns a sentinel that will observe the given sequence of online cpus.
+    fn sentinel(sequence: &[&[u32]]) -> Sentinel<MockStatClock, MockStatFile> {
+        let stats = sequence
+            .iter()
+            .zip(1..)
+            .map(|(online, i)| MockStatFile::render(online.iter().copied(), i * 100));
+        let clock = MockStatClock::ticking(Duration::from_secs(1), sequence.len() as u32);
+        Sentinel::from_parts(clock, MockStatFile::new(stats))
+    }
+
+    fn ids(ids: &[u32]) -> BTreeSet<CpuId> {
+        ids.iter().copied().map(CpuId::from).collect()
+    }
+
+    #[test]
+    fn stable() {
+        let mut sentinel = sentinel(&[&[0, 1], &[0, 1]]);
+        assert!(sentinel.observe().unwrap().is_none());
+        let recording = sentinel.observe().unwrap().unwrap();
+        assert_eq!(
+            recording.cpus().keys().copied().collect::<BTreeSet<_>>(),
+            ids(&[0, 1])
+        );
+        assert!(recording.appeared().is_empty());
+        assert!(recording.disappeared().is_empty());
+    }
+
+    #[test]
+    fn offline() {
+        let mut sentinel = sentinel(&[&[0, 1, 2], &[0, 2]]);
+        assert!(sentinel.observe().unwrap().is_none());
+        let recording = sentinel.observe().unwrap().unwrap();
+        assert_eq!(
+            recording.cpus().keys().copied().collect::<BTreeSet<_>>(),
+            ids(&[0, 2])
+        );
+        assert!(recording.appeared().is_empty());
+        assert_eq!(*recording.disappeared(), ids(&[1]));
+    }
+
+    #[test]
+    fn online() {
+        let mut sentinel = sentinel(&[&[0], &[0, 1]]);
+        assert!(sentinel.observe().unwrap().is_none());
+        let recording = sentinel.observe().unwrap().unwrap();
+        assert_eq!(
+            recording.cpus().keys().copied().collect::<BTreeSet<_>>(),
+            ids(&[0])
+        );
+        assert_eq!(*recording.appeared(), ids(&[1]));
+        assert!(recording.disappeared().is_empty());
+    }
+
+    /// a cpu goes offline, and then comes back.
+    #[test]
+    fn offline_and_back() {
+        let mut sentinel = sentinel(&[&[0, 1], &[0], &[0, 1], &[0, 1]]);
+        assert!(sentinel.observe().unwrap().is_none());
+
+        let recording = sentinel.observe().unwrap().unwrap();
+        assert_eq!(*recording.disappeared(), ids(&[1]));
+
+        let recording = sentinel.observe().unwrap().unwrap();
+        assert_eq!(*recording.appeared(), ids(&[1]));
+        assert_eq!(recording.cpus().len(), 1);
+
+        let recording = sentinel.observe().unwrap().unwrap();
+        assert_eq!(
+            recording.cpus().keys().copied().collect::<BTreeSet<_>>(),
+            ids(&[0, 1])
+        );
+    }
+
+    /// every cpu is replaced by another.
+    #[test]
+    fn disjoint() {
+        let mut sentinel = sentinel(&[&[0, 1], &[2, 3]]);
+        assert!(sentinel.observe().unwrap().is_none());
+        let recording = sentinel.observe().unwrap().unwrap();
+        assert!(recording.cpus().is_empty());
+        assert_eq!(*recording.appeared(), ids(&[2, 3]));
+        assert_eq!(*recording.disappeared(), ids(&[0, 1]));
+    }
+}
+
+mod clock_tests {
+    use super::*;
+
+    /// a clock that does not advance is reported as an error, rather than a panic.
+    #[test]
+    fn stopped() {
+        let now = Instant::now();
+        let stats = (1..=3).map(|i| MockStatFile::render([0], i * 100));
+        let clock = MockStatClock::new([now, now, now + Duration::from_secs(1)]);
+        let mut sentinel = Sentinel::from_parts(clock, MockStatFile::new(stats));
+
+        assert!(sentinel.observe().unwrap().is_none());
+        assert!(matches!(
+            sentinel.observe(),
+            Err(StatReadError::NonMonotonic)
+        ));
+
+        // the next recording begins at the reading that was rejected.
+        let recording = sentinel.observe().unwrap().unwrap();
+        assert_eq!(recording.elapsed(), Duration::from_secs(1));
+    }
+}
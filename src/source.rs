@@ -3,18 +3,24 @@ use std::{
     collections::VecDeque,
     fs::File,
     io::{self, BufReader, Cursor, Read},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
-pub use self::{clock::*, stats::*};
+pub use self::{
+    clock::{Clock, SystemClock},
+    stats::{ProcStatFile, StatsSource},
+};
 
 mod clock {
     use super::*;
 
+    /// a source of time.
     pub trait Clock {
+        /// returns the current time.
         fn now(&self) -> Instant;
     }
 
+    /// the system's monotonic clock.
     #[derive(Default)]
     pub struct SystemClock;
 
@@ -23,24 +29,6 @@ mod clock {
             Instant::now()
         }
     }
-
-    /// a mock stat source.
-    #[derive(Default)]
-    #[allow(dead_code, reason = "this is a testing utility.")]
-    pub struct MockStatClock {
-        times: RefCell<VecDeque<Instant>>,
-    }
-
-    impl Clock for MockStatClock {
-        fn now(&self) -> Instant {
-            let MockStatClock { times } = self;
-
-            times
-                .borrow_mut()
-                .pop_front()
-                .expect("mock times should not be empty")
-        }
-    }
 }
 
 /// abstracts over providers of statistics.
@@ -48,8 +36,10 @@ mod stats {
     use super::*;
 
     /// a source of kernel statistics.
+    ///
+    /// statistics should be formatted like `/proc/stat`; see `proc_stat(5)`.
     pub trait StatsSource {
-        /// returns a reader.
+        /// returns a reader of the current statistics.
         fn open(&self) -> io::Result<impl Read>;
     }
 
@@ -57,13 +47,6 @@ mod stats {
     #[derive(Default)]
     pub struct ProcStatFile;
 
-    /// a mock stat source.
-    #[derive(Default)]
-    #[allow(dead_code, reason = "this is a testing utility.")]
-    pub struct MockStatFile {
-        stats: RefCell<VecDeque<String>>,
-    }
-
     // === impl ProcStatFile ===
 
     impl StatsSource for ProcStatFile {
@@ -75,9 +58,89 @@ mod stats {
     impl ProcStatFile {
         const STAT: &str = "/proc/stat";
     }
+}
+
+/// mock sources of time and statistics, for tests.
+pub mod mock {
+    use super::*;
+
+    /// a mock clock, which reports a scripted sequence of times.
+    ///
+    /// this will panic if more times are requested than were provided.
+    #[derive(Default)]
+    pub struct MockStatClock {
+        times: RefCell<VecDeque<Instant>>,
+    }
+
+    impl MockStatClock {
+        /// returns a new mock clock that will report the given times, in order.
+        pub fn new(times: impl IntoIterator<Item = Instant>) -> Self {
+            Self {
+                times: RefCell::new(times.into_iter().collect()),
+            }
+        }
+
+        /// returns a new mock clock that will report `n` times, `interval` apart.
+        pub fn ticking(interval: Duration, n: u32) -> Self {
+            let start = Instant::now();
+            Self::new((0..n).map(|i| start + interval * i))
+        }
+
+        /// adds a time to the end of the queue.
+        pub fn push(&self, time: Instant) {
+            self.times.borrow_mut().push_back(time);
+        }
+    }
+
+    impl Clock for MockStatClock {
+        fn now(&self) -> Instant {
+            let MockStatClock { times } = self;
+
+            times
+                .borrow_mut()
+                .pop_front()
+                .expect("mock times should not be empty")
+        }
+    }
+
+    /// a mock stat source, which yields a scripted sequence of statistics.
+    ///
+    /// this will panic if more statistics are requested than were provided.
+    #[derive(Default)]
+    pub struct MockStatFile {
+        stats: RefCell<VecDeque<String>>,
+    }
 
     // === impl MockStatFile ===
 
+    impl MockStatFile {
+        /// returns a new mock source that will yield the given statistics, in order.
+        pub fn new(stats: impl IntoIterator<Item = impl Into<String>>) -> Self {
+            Self {
+                stats: RefCell::new(stats.into_iter().map(Into::into).collect()),
+            }
+        }
+
+        /// adds statistics to the end of the queue.
+        pub fn push(&self, stats: impl Into<String>) {
+            self.stats.borrow_mut().push_back(stats.into());
+        }
+
+        /// renders the statistics of a system whose online cpus each spent the given time in
+        /// each state.
+        ///
+        /// this is useful for scripting cpu hotplug, by omitting cpus that are offline.
+        pub fn render(online: impl IntoIterator<Item = u32>, ticks: u64) -> String {
+            let online = online.into_iter().collect::<Vec<_>>();
+            let all = ticks * online.len() as u64;
+            let line = |kind: &str, t: u64| format!("{kind} {t} {t} {t} {t} {t} {t} {t} 0 0 0\n");
+
+            std::iter::once(line("cpu", all))
+                .chain(online.iter().map(|id| line(&format!("cpu{id}"), ticks)))
+                .collect()
+        }
+    }
+
     impl StatsSource for MockStatFile {
         fn open(&self) -> io::Result<impl Read> {
             let Self { stats } = self;
@@ -11,10 +11,14 @@ use {
 };
 
 pub use self::{
-    cpu_time::{CpuTime, Measurement},
-    user_hz::UserHz,
+    accounting::Accounting,
+    counters::{Counters, Rates},
+    cpu_time::{CpuTime, Measurement, State},
+    user_hz::{TickRate, UserHz},
 };
 
+mod accounting;
+mod counters;
 mod cpu_time;
 mod user_hz;
 
@@ -22,11 +26,16 @@ mod user_hz;
 mod tests;
 
 /// a snapshot of the cpus' statistics at a moment in time.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Snapshot {
-    pub system: CpuTime,
-    pub cpus: BTreeMap<CpuId, CpuTime>,
-    pub time: Instant,
+    /// the amount of time that all cpus spent in various states, in aggregate.
+    system: CpuTime,
+    /// the amount of time that each online cpu spent in various states.
+    cpus: BTreeMap<CpuId, CpuTime>,
+    /// system-wide counters, such as context switches and forks.
+    counters: Counters,
+    /// when this snapshot was taken.
+    time: Instant,
 }
 
 /// an entry in the `/proc/stat` kernel statistics table.
@@ -36,48 +45,122 @@ pub struct Snapshot {
 pub enum Entry {
     /// the amount of time that the system ("cpu" line) spent in various states.
     AllCpu {
+        /// the time spent in each state, across all cpus.
         time: CpuTime,
     },
     /// the amount of time that a specific cpu ("cpuN" line) spent in various states.
     Cpu {
+        /// the cpu's id.
         id: CpuId,
+        /// the time spent in each state.
         time: CpuTime,
     },
     /// the number of pages the system paged in and the number that were paged out (from disk).
-    Page,
+    Page {
+        /// pages paged in.
+        paged_in: u64,
+        /// pages paged out.
+        paged_out: u64,
+    },
     /// the number of swap pages that have been brought in and out.
-    Swap,
+    Swap {
+        /// swap pages brought in.
+        swapped_in: u64,
+        /// swap pages brought out.
+        swapped_out: u64,
+    },
     /// this line shows counts of interrupts serviced since boot time.
-    Intr,
+    ///
+    /// the first column is the total of all interrupts serviced. each subsequent column is the
+    /// total for a particular interrupt; those are not kept.
+    Intr {
+        /// interrupts serviced since boot.
+        total: u64,
+    },
+    /// disk i/o statistics. (linux 2.4 only.)
     DiskIo,
     /// the number of context switches that the system underwent.
-    Ctxt,
-    Btime,
+    Ctxt {
+        /// context switches since boot.
+        switches: u64,
+    },
+    /// boot time, in seconds since the epoch.
+    Btime {
+        /// seconds since the epoch.
+        seconds: u64,
+    },
     /// the number of forks since boot.
-    Processes,
+    Processes {
+        /// forks since boot.
+        forks: u64,
+    },
     /// the number of processes in runnable state.  (linux 2.5.45 onward.)
-    ProcsRunning,
+    ProcsRunning {
+        /// runnable processes.
+        count: u64,
+    },
     /// the number of processes blocked waiting for i/o to complete.
-    ProcsBlocked,
+    ProcsBlocked {
+        /// blocked processes.
+        count: u64,
+    },
     /// this line shows the number of softirq for all cpus.
-    SoftIrq,
+    ///
+    /// the first column is the total of all softirqs serviced. each subsequent column is the
+    /// total for a particular softirq; those are not kept.
+    SoftIrq {
+        /// softirqs serviced since boot.
+        total: u64,
+    },
 }
 
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
-pub struct CpuId(u8);
+/// the id of a cpu, such as `3` in a "cpu3" line.
+///
+/// linux supports up to 8192 cpus (see `CONFIG_NR_CPUS`), so ids are kept as 32-bit integers.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CpuId(u32);
 
+/// an error parsing a line of `/proc/stat`.
 #[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum EntryParseError {
-    UnrecognizedEntry { kind: String },
-    CpuIdParse(<u8 as FromStr>::Err),
+    /// the line was empty.
+    Empty,
+    /// the line began with an unrecognized kind of entry.
+    UnrecognizedEntry {
+        /// the first token of the line.
+        kind: String,
+    },
+    /// the id of a "cpuN" line could not be parsed.
+    CpuIdParse(<u32 as FromStr>::Err),
+    /// a time column of a cpu line could not be parsed.
     UserHzParse(<UserHz as FromStr>::Err),
-    CpuTime,
+    /// a counter of a non-cpu line could not be parsed.
+    CounterParse(<u64 as FromStr>::Err),
+    /// a non-cpu line had fewer counters than expected.
+    MissingCounter {
+        /// the kind of entry.
+        kind: String,
+    },
+    /// a cpu line had fewer than the four required time columns.
+    CpuTime {
+        /// the number of time columns that were present.
+        columns: usize,
+    },
 }
 
+/// an error reading a [`Snapshot`].
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum StatReadError {
+    /// the statistics could not be read.
     Io(io::Error),
+    /// a line of the statistics could not be parsed.
     Entry(EntryParseError),
+    /// the statistics did not include the aggregate "cpu" line.
+    MissingSystem,
+    /// the clock did not advance between two snapshots.
+    NonMonotonic,
 }
 
 enum Either<'a> {
@@ -89,10 +172,7 @@ enum Either<'a> {
 
 impl Snapshot {
     /// uses the given source to parse a snapshot of the cpu statistics.
-    pub(super) fn read(
-        stats: &impl StatsSource,
-        clock: &impl Clock,
-    ) -> Result<Snapshot, StatReadError> {
+    pub fn read(stats: &impl StatsSource, clock: &impl Clock) -> Result<Snapshot, StatReadError> {
         let time = clock.now();
         let stats = {
             let reader = stats.open().map_err(StatReadError::Io)?;
@@ -110,21 +190,50 @@ impl Snapshot {
             entries.push(entry);
         }
 
-        let (system, cpus) =
-            entries
-                .into_iter()
-                .fold((None, BTreeMap::default()), |(mut sys, mut cpus), entry| {
-                    match entry {
-                        Entry::Cpu { id, time } => cpus.insert(id, time),
-                        Entry::AllCpu { time } => sys.replace(time),
-                        _ => None,
-                    };
-                    (sys, cpus)
-                });
-
-        let system = system.expect("system cpu statistic should exist");
-
-        Ok(Snapshot { system, cpus, time })
+        let (system, cpus, counters) = entries.into_iter().fold(
+            (None, BTreeMap::default(), Counters::default()),
+            |(mut sys, mut cpus, mut counters), entry| {
+                match entry {
+                    Entry::Cpu { id, time } => {
+                        cpus.insert(id, time);
+                    }
+                    Entry::AllCpu { time } => {
+                        sys.replace(time);
+                    }
+                    other => counters.insert(&other),
+                }
+                (sys, cpus, counters)
+            },
+        );
+
+        let system = system.ok_or(StatReadError::MissingSystem)?;
+
+        Ok(Snapshot {
+            system,
+            cpus,
+            counters,
+            time,
+        })
+    }
+
+    /// returns the amount of time that all cpus spent in various states, in aggregate.
+    pub fn system(&self) -> &CpuTime {
+        &self.system
+    }
+
+    /// returns the amount of time that each online cpu spent in various states.
+    pub fn cpus(&self) -> &BTreeMap<CpuId, CpuTime> {
+        &self.cpus
+    }
+
+    /// returns the system-wide counters, such as context switches and forks.
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    /// returns when this snapshot was taken.
+    pub fn time(&self) -> Instant {
+        self.time
     }
 }
 
@@ -138,16 +247,16 @@ impl FromStr for Entry {
             .filter(|t| t.is_empty().not())
             .collect::<Vec<_>>();
         let [kind, tokens @ ..] = tokens.as_slice() else {
-            todo!()
+            return Err(EntryParseError::Empty);
         };
 
-        let id = match Self::parse_entry_kind(kind) {
+        let id = match Self::parse_entry_kind(kind, tokens)? {
             Either::Cpu(cpu) => Self::parse_cpu_id(cpu)?,
             Either::Entry(entry) => return Ok(entry),
         };
 
         let time = tokens
-            .into_iter()
+            .iter()
             .map(Deref::deref)
             .map(str::parse::<UserHz>)
             .collect::<Result<Vec<_>, _>>()
@@ -163,22 +272,78 @@ impl FromStr for Entry {
 }
 
 impl Entry {
-    fn parse_entry_kind(kind: &str) -> Either<'_> {
+    fn parse_entry_kind<'a>(kind: &'a str, tokens: &[&str]) -> Result<Either<'a>, EntryParseError> {
         use Entry::*;
 
-        match kind {
-            "page" => Either::Entry(Page),
-            "swap" => Either::Entry(Swap),
-            "intr" => Either::Entry(Intr),
-            "disk_io" => Either::Entry(DiskIo),
-            "ctxt" => Either::Entry(Ctxt),
-            "btime" => Either::Entry(Btime),
-            "processes" => Either::Entry(Processes),
-            "procs_running" => Either::Entry(ProcsRunning),
-            "procs_blocked" => Either::Entry(ProcsBlocked),
-            "softirq" => Either::Entry(SoftIrq),
-            cpu => Either::Cpu(cpu),
+        let entry = match kind {
+            "page" => {
+                let [paged_in, paged_out] = Self::parse_counters(kind, tokens)?;
+                Page {
+                    paged_in,
+                    paged_out,
+                }
+            }
+            "swap" => {
+                let [swapped_in, swapped_out] = Self::parse_counters(kind, tokens)?;
+                Swap {
+                    swapped_in,
+                    swapped_out,
+                }
+            }
+            "intr" => {
+                let [total] = Self::parse_counters(kind, tokens)?;
+                Intr { total }
+            }
+            "disk_io" => DiskIo,
+            "ctxt" => {
+                let [switches] = Self::parse_counters(kind, tokens)?;
+                Ctxt { switches }
+            }
+            "btime" => {
+                let [seconds] = Self::parse_counters(kind, tokens)?;
+                Btime { seconds }
+            }
+            "processes" => {
+                let [forks] = Self::parse_counters(kind, tokens)?;
+                Processes { forks }
+            }
+            "procs_running" => {
+                let [count] = Self::parse_counters(kind, tokens)?;
+                ProcsRunning { count }
+            }
+            "procs_blocked" => {
+                let [count] = Self::parse_counters(kind, tokens)?;
+                ProcsBlocked { count }
+            }
+            "softirq" => {
+                let [total] = Self::parse_counters(kind, tokens)?;
+                SoftIrq { total }
+            }
+            cpu => return Ok(Either::Cpu(cpu)),
+        };
+
+        Ok(Either::Entry(entry))
+    }
+
+    /// parses the first `N` counters following an entry's kind.
+    ///
+    /// any columns after the first `N` are ignored.
+    fn parse_counters<const N: usize>(
+        kind: &str,
+        tokens: &[&str],
+    ) -> Result<[u64; N], EntryParseError> {
+        use EntryParseError::{CounterParse, MissingCounter};
+
+        let tokens = tokens.get(..N).ok_or_else(|| MissingCounter {
+            kind: kind.to_owned(),
+        })?;
+
+        let mut counters = [0; N];
+        for (counter, token) in counters.iter_mut().zip(tokens) {
+            *counter = token.parse().map_err(CounterParse)?;
         }
+
+        Ok(counters)
     }
 
     fn parse_cpu_id(token: &str) -> Result<Option<CpuId>, EntryParseError> {
@@ -196,7 +361,7 @@ impl Entry {
 
         // parse the id into an integer.
         suffix
-            .parse::<u8>()
+            .parse::<u32>()
             .map(CpuId)
             .map(Some)
             .map_err(CpuIdParse)
@@ -206,8 +371,21 @@ impl Entry {
 // === impl CpuId ===
 
 impl CpuId {
-    pub fn as_u16(&self) -> u16 {
-        self.0 as u16
+    /// returns the cpu's id, as an integer.
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for CpuId {
+    fn from(id: u32) -> Self {
+        Self(id)
+    }
+}
+
+impl Display for CpuId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("cpu{}", self.0))
     }
 }
 
@@ -218,6 +396,7 @@ impl std::error::Error for StatReadError {
         match self {
             Self::Io(io) => Some(io),
             Self::Entry(entry) => Some(entry),
+            Self::MissingSystem | Self::NonMonotonic => None,
         }
     }
 }
@@ -227,6 +406,8 @@ impl Display for StatReadError {
         match self {
             Self::Io(io) => f.write_fmt(format_args!("{}", io)),
             Self::Entry(entry) => f.write_fmt(format_args!("{}", entry)),
+            Self::MissingSystem => f.write_str("missing aggregate cpu statistics"),
+            Self::NonMonotonic => f.write_str("the clock did not advance between two snapshots"),
         }
     }
 }
@@ -249,12 +430,17 @@ impl fmt::Display for EntryParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use EntryParseError::*;
         match self {
+            Empty => f.write_str("empty entry"),
             UnrecognizedEntry { kind } => {
                 f.write_fmt(format_args!("unrecognized entry kind: {kind}"))
             }
             CpuIdParse(error) => f.write_fmt(format_args!("invalid cpu id: {error}")),
             UserHzParse(error) => f.write_fmt(format_args!("invalid time value: {error}")),
-            CpuTime => f.write_str("some other error"), // XXX(kate)
+            CounterParse(error) => f.write_fmt(format_args!("invalid counter value: {error}")),
+            MissingCounter { kind } => f.write_fmt(format_args!("missing counter value: {kind}")),
+            CpuTime { columns } => f.write_fmt(format_args!(
+                "expected at least four cpu time columns, found {columns}"
+            )),
         }
     }
 }
@@ -266,7 +452,11 @@ impl std::error::Error for EntryParseError {
         match self {
             CpuIdParse(error) => Some(error),
             UserHzParse(error) => Some(error),
-            UnrecognizedEntry { kind: _ } | CpuTime => None,
+            CounterParse(error) => Some(error),
+            Empty
+            | UnrecognizedEntry { kind: _ }
+            | MissingCounter { kind: _ }
+            | CpuTime { columns: _ } => None,
         }
     }
 }
@@ -0,0 +1,52 @@
+/// decides which cpu states count as busy.
+///
+/// user, nice, system, irq, and softirq time are always busy, and idle time never is. guest time
+/// is already included in user and nice time, so it is never counted a second time.
+///
+/// by default, iowait is counted as idle and steal as busy, matching `top` and `mpstat`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Accounting {
+    /// whether time waiting for i/o to complete counts as busy.
+    iowait_busy: bool,
+    /// whether time stolen by the hypervisor counts as busy.
+    steal_busy: bool,
+}
+
+// === impl Accounting ===
+
+impl Default for Accounting {
+    fn default() -> Self {
+        Self {
+            iowait_busy: false,
+            steal_busy: true,
+        }
+    }
+}
+
+impl Accounting {
+    /// counts time waiting for i/o to complete as busy, if `busy` is `true`.
+    pub fn with_iowait_busy(self, busy: bool) -> Self {
+        Self {
+            iowait_busy: busy,
+            ..self
+        }
+    }
+
+    /// counts time stolen by the hypervisor as busy, if `busy` is `true`.
+    pub fn with_steal_busy(self, busy: bool) -> Self {
+        Self {
+            steal_busy: busy,
+            ..self
+        }
+    }
+
+    /// returns `true` if time waiting for i/o to complete counts as busy.
+    pub fn iowait_busy(&self) -> bool {
+        self.iowait_busy
+    }
+
+    /// returns `true` if time stolen by the hypervisor counts as busy.
+    pub fn steal_busy(&self) -> bool {
+        self.steal_busy
+    }
+}
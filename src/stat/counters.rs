@@ -0,0 +1,223 @@
+use {super::*, std::time::Duration};
+
+/// system-wide counters, read from the non-cpu lines of `/proc/stat`.
+///
+/// not every kernel reports every line, so each of these is optional.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Counters {
+    /// the number of pages paged in (from disk).
+    paged_in: Option<u64>,
+    /// the number of pages paged out (to disk).
+    paged_out: Option<u64>,
+    /// the number of swap pages brought in.
+    swapped_in: Option<u64>,
+    /// the number of swap pages brought out.
+    swapped_out: Option<u64>,
+    /// the number of interrupts serviced since boot.
+    interrupts: Option<u64>,
+    /// the number of context switches since boot.
+    context_switches: Option<u64>,
+    /// boot time, in seconds since the epoch.
+    boot_time: Option<u64>,
+    /// the number of forks since boot.
+    forks: Option<u64>,
+    /// the number of processes in runnable state.
+    procs_running: Option<u64>,
+    /// the number of processes blocked waiting for i/o to complete.
+    procs_blocked: Option<u64>,
+    /// the number of softirqs serviced since boot.
+    softirqs: Option<u64>,
+}
+
+/// the rate at which [`Counters`] changed between two snapshots.
+///
+/// rates are given per second. a rate is `None` if the counter was not reported by both
+/// snapshots, or if it decreased.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rates {
+    /// pages paged in per second.
+    paged_in: Option<f64>,
+    /// pages paged out per second.
+    paged_out: Option<f64>,
+    /// swap pages brought in per second.
+    swapped_in: Option<f64>,
+    /// swap pages brought out per second.
+    swapped_out: Option<f64>,
+    /// interrupts serviced per second.
+    interrupts: Option<f64>,
+    /// context switches per second.
+    context_switches: Option<f64>,
+    /// forks per second.
+    forks: Option<f64>,
+    /// softirqs serviced per second.
+    softirqs: Option<f64>,
+    /// the number of processes in runnable state, at the end of the interval.
+    procs_running: Option<u64>,
+    /// the number of processes blocked waiting for i/o, at the end of the interval.
+    procs_blocked: Option<u64>,
+}
+
+// === impl Counters ===
+
+impl Counters {
+    /// records the value(s) carried by the given entry.
+    ///
+    /// cpu entries, and entries that do not carry a counter, are ignored.
+    pub fn insert(&mut self, entry: &Entry) {
+        match *entry {
+            Entry::Page {
+                paged_in,
+                paged_out,
+            } => {
+                self.paged_in = Some(paged_in);
+                self.paged_out = Some(paged_out);
+            }
+            Entry::Swap {
+                swapped_in,
+                swapped_out,
+            } => {
+                self.swapped_in = Some(swapped_in);
+                self.swapped_out = Some(swapped_out);
+            }
+            Entry::Intr { total } => self.interrupts = Some(total),
+            Entry::Ctxt { switches } => self.context_switches = Some(switches),
+            Entry::Btime { seconds } => self.boot_time = Some(seconds),
+            Entry::Processes { forks } => self.forks = Some(forks),
+            Entry::ProcsRunning { count } => self.procs_running = Some(count),
+            Entry::ProcsBlocked { count } => self.procs_blocked = Some(count),
+            Entry::SoftIrq { total } => self.softirqs = Some(total),
+            Entry::AllCpu { .. } | Entry::Cpu { .. } | Entry::DiskIo => {}
+        }
+    }
+
+    /// returns the number of pages paged in (from disk), if it was reported.
+    pub fn paged_in(&self) -> Option<u64> {
+        self.paged_in
+    }
+
+    /// returns the number of pages paged out (to disk), if it was reported.
+    pub fn paged_out(&self) -> Option<u64> {
+        self.paged_out
+    }
+
+    /// returns the number of swap pages brought in, if it was reported.
+    pub fn swapped_in(&self) -> Option<u64> {
+        self.swapped_in
+    }
+
+    /// returns the number of swap pages brought out, if it was reported.
+    pub fn swapped_out(&self) -> Option<u64> {
+        self.swapped_out
+    }
+
+    /// returns the number of interrupts serviced since boot, if it was reported.
+    pub fn interrupts(&self) -> Option<u64> {
+        self.interrupts
+    }
+
+    /// returns the number of context switches since boot, if it was reported.
+    pub fn context_switches(&self) -> Option<u64> {
+        self.context_switches
+    }
+
+    /// returns the boot time, in seconds since the epoch, if it was reported.
+    pub fn boot_time(&self) -> Option<u64> {
+        self.boot_time
+    }
+
+    /// returns the number of forks since boot, if it was reported.
+    pub fn forks(&self) -> Option<u64> {
+        self.forks
+    }
+
+    /// returns the number of processes in runnable state, if it was reported.
+    pub fn procs_running(&self) -> Option<u64> {
+        self.procs_running
+    }
+
+    /// returns the number of processes blocked waiting for i/o to complete, if it was reported.
+    pub fn procs_blocked(&self) -> Option<u64> {
+        self.procs_blocked
+    }
+
+    /// returns the number of softirqs serviced since boot, if it was reported.
+    pub fn softirqs(&self) -> Option<u64> {
+        self.softirqs
+    }
+}
+
+// === impl Rates ===
+
+impl Rates {
+    /// computes the rates of change between two sets of counters, `elapsed` apart.
+    pub fn new(a: Counters, b: Counters, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        let rate = |a: Option<u64>, b: Option<u64>| -> Option<f64> {
+            let delta = b?.checked_sub(a?)?;
+            (secs > 0.0).then(|| delta as f64 / secs)
+        };
+
+        Self {
+            paged_in: rate(a.paged_in, b.paged_in),
+            paged_out: rate(a.paged_out, b.paged_out),
+            swapped_in: rate(a.swapped_in, b.swapped_in),
+            swapped_out: rate(a.swapped_out, b.swapped_out),
+            interrupts: rate(a.interrupts, b.interrupts),
+            context_switches: rate(a.context_switches, b.context_switches),
+            forks: rate(a.forks, b.forks),
+            softirqs: rate(a.softirqs, b.softirqs),
+            procs_running: b.procs_running,
+            procs_blocked: b.procs_blocked,
+        }
+    }
+
+    /// returns the pages paged in per second.
+    pub fn paged_in(&self) -> Option<f64> {
+        self.paged_in
+    }
+
+    /// returns the pages paged out per second.
+    pub fn paged_out(&self) -> Option<f64> {
+        self.paged_out
+    }
+
+    /// returns the swap pages brought in per second.
+    pub fn swapped_in(&self) -> Option<f64> {
+        self.swapped_in
+    }
+
+    /// returns the swap pages brought out per second.
+    pub fn swapped_out(&self) -> Option<f64> {
+        self.swapped_out
+    }
+
+    /// returns the interrupts serviced per second.
+    pub fn interrupts(&self) -> Option<f64> {
+        self.interrupts
+    }
+
+    /// returns the context switches per second.
+    pub fn context_switches(&self) -> Option<f64> {
+        self.context_switches
+    }
+
+    /// returns the forks per second.
+    pub fn forks(&self) -> Option<f64> {
+        self.forks
+    }
+
+    /// returns the softirqs serviced per second.
+    pub fn softirqs(&self) -> Option<f64> {
+        self.softirqs
+    }
+
+    /// returns the number of processes in runnable state, at the end of the interval.
+    pub fn procs_running(&self) -> Option<u64> {
+        self.procs_running
+    }
+
+    /// returns the number of processes blocked waiting for i/o, at the end of the interval.
+    pub fn procs_blocked(&self) -> Option<u64> {
+        self.procs_blocked
+    }
+}
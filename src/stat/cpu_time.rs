@@ -1,5 +1,37 @@
-use super::*;
+use {
+    super::*,
+    std::{cell::Cell, time::Duration},
+};
 
+/// a state that a cpu can spend its time in.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum State {
+    /// user mode.
+    User,
+    /// user mode with low priority (nice).
+    Nice,
+    /// system mode.
+    System,
+    /// the idle task.
+    Idle,
+    /// waiting for i/o to complete.
+    Iowait,
+    /// servicing interrupts.
+    Irq,
+    /// servicing softirqs.
+    Softirq,
+    /// running other operating systems, when running in a virtualized environment.
+    Steal,
+    /// running a virtual cpu for a guest operating system.
+    Guest,
+    /// running a niced virtual cpu for a guest operating system.
+    GuestNice,
+}
+
+/// the amount of time that a cpu spent in various states.
+///
+/// the first four columns (user, nice, system, and idle) are always present. older kernels do not
+/// report the later columns, so each of those is `None` when the kernel did not report it.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct CpuTime {
     /// time spent in user mode.
@@ -21,23 +53,25 @@ pub struct CpuTime {
     ///   *  on a multi-core cpu, the task waiting for i/o to complete is not running on any cpu,
     ///      so the iowait of each cpu is difficult to calculate.
     ///   *  the value in this field may decrease in certain conditions.
-    iowait: UserHz,
+    iowait: Option<UserHz>,
     /// time servicing interrupts.
-    irq: UserHz,
+    irq: Option<UserHz>,
     /// time servicing softirqs.
-    softirq: UserHz,
+    softirq: Option<UserHz>,
     /// stolen time, which is the time spent in other operating systems when running in a
     /// virtualized environment.
-    steal: UserHz,
+    steal: Option<UserHz>,
     /// time spent running a virtual cpu for guest operating systems under the control of the linux
     /// kernel.
-    guest: UserHz,
+    guest: Option<UserHz>,
     /// time spent running a niced guest (virtual cpu for guest operating systems under the
     /// control of the linux kernel).
-    guest_nice: UserHz,
+    guest_nice: Option<UserHz>,
 }
 
 /// a measurement of the difference between two [`CpuTime`]s.
+///
+/// states that were not reported by both [`CpuTime`]s are `None`.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Measurement {
     /// time spent in user mode.
@@ -59,86 +93,108 @@ pub struct Measurement {
     ///   *  on a multi-core cpu, the task waiting for i/o to complete is not running on any cpu,
     ///      so the iowait of each cpu is difficult to calculate.
     ///   *  the value in this field may decrease in certain conditions.
-    iowait: UserHz,
+    iowait: Option<UserHz>,
     /// time servicing interrupts.
-    irq: UserHz,
+    irq: Option<UserHz>,
     /// time servicing softirqs.
-    softirq: UserHz,
+    softirq: Option<UserHz>,
     /// stolen time, which is the time spent in other operating systems when running in a
     /// virtualized environment.
-    steal: UserHz,
+    steal: Option<UserHz>,
     /// time spent running a virtual cpu for guest operating systems under the control of the linux
     /// kernel.
-    guest: UserHz,
+    guest: Option<UserHz>,
     /// time spent running a niced guest (virtual cpu for guest operating systems under the
     /// control of the linux kernel).
-    guest_nice: UserHz,
+    guest_nice: Option<UserHz>,
+    /// whether every counter grew (or stayed the same) between the two [`CpuTime`]s.
+    ///
+    /// counters that went backwards are clamped to zero.
+    trustworthy: bool,
+    /// which states count as busy.
+    accounting: Accounting,
 }
 
 // == impl Measurement ===
 
 impl Measurement {
+    /// measures the time spent in each state between `a` and `b`.
     pub fn new(a: CpuTime, b: CpuTime) -> Self {
-        let a: [_; 10] = a.into();
-        let b: [_; 10] = b.into();
-
-        let CpuTime {
-            user,
-            nice,
-            system,
-            idle,
-            iowait,
-            irq,
-            softirq,
-            steal,
-            guest,
-            guest_nice,
-        } = a
-            .iter()
-            .enumerate()
-            .map(|(i, a_i)| {
-                let b_i = b[i];
-                b_i - *a_i
+        // a counter that went backwards is clamped to zero, and flags this measurement.
+        let trustworthy = Cell::new(true);
+        let diff = |a: UserHz, b: UserHz| {
+            b.checked_sub(a).unwrap_or_else(|| {
+                trustworthy.set(false);
+                b - a
             })
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
+        };
+        let diff_opt = |a: Option<UserHz>, b: Option<UserHz>| a.zip(b).map(|(a, b)| diff(a, b));
 
         Self {
-            user,
-            nice,
-            system,
-            idle,
-            iowait,
-            irq,
-            softirq,
-            steal,
-            guest,
-            guest_nice,
+            user: diff(a.user, b.user),
+            nice: diff(a.nice, b.nice),
+            system: diff(a.system, b.system),
+            idle: diff(a.idle, b.idle),
+            iowait: diff_opt(a.iowait, b.iowait),
+            irq: diff_opt(a.irq, b.irq),
+            softirq: diff_opt(a.softirq, b.softirq),
+            steal: diff_opt(a.steal, b.steal),
+            guest: diff_opt(a.guest, b.guest),
+            guest_nice: diff_opt(a.guest_nice, b.guest_nice),
+            trustworthy: trustworthy.get(),
+            accounting: Accounting::default(),
         }
     }
 
-    /// returns the percentage of active cpu time.
-    pub fn percentage(&self) -> u8 {
-        self.normalized(100)
+    /// decides which states count as busy using `accounting`.
+    pub fn with_accounting(self, accounting: Accounting) -> Self {
+        Self { accounting, ..self }
     }
 
-    pub fn normalized(&self, n: usize) -> u8 {
-        let active = self.active();
-        let total = self.total();
+    /// returns the model deciding which states count as busy.
+    pub fn accounting(&self) -> Accounting {
+        self.accounting
+    }
+
+    /// returns `true` if every counter grew (or stayed the same) during this measurement.
+    ///
+    /// if a counter went backwards, its time is counted as zero and this returns `false`.
+    pub fn is_trustworthy(&self) -> bool {
+        self.trustworthy
+    }
 
-        // calculate the ratio of active time.
-        let norm = (active / total) * n as f64;
-        assert!(norm >= 0.0);
-        assert!(norm <= n as f64);
+    /// returns the ratio of active cpu time to total cpu time, from zero to one.
+    ///
+    /// returns `None` if no time passed during this measurement, which can happen when sampling
+    /// faster than the clock ticks. the ratio is not rounded; formatting it as a percentage, or in
+    /// basis points, is left to the caller.
+    pub fn ratio(&self) -> Option<f64> {
+        let total = self.total();
+        (total.get() > 0).then(|| (self.active() / total).clamp(0.0, 1.0))
+    }
 
-        // round to the nearest digit.
-        let rounded: u8 = norm.round() as u8;
-        assert!(rounded <= 100);
+    /// returns the percentage of active cpu time, rounded to the nearest whole percent.
+    ///
+    /// this is zero if no time passed. see [`Measurement::ratio()`] for a precise value.
+    pub fn percentage(&self) -> u8 {
+        self.normalized(100)
+    }
 
-        rounded
+    /// returns the ratio of active cpu time, scaled to `0..=n` and rounded.
+    ///
+    /// this is zero if no time passed, and saturates at `u8::MAX`. see [`Measurement::ratio()`]
+    /// for a precise value.
+    pub fn normalized(&self, n: usize) -> u8 {
+        self.ratio().map_or(0, |ratio| {
+            (ratio * n as f64).round().min(f64::from(u8::MAX)) as u8
+        })
     }
 
+    /// returns the active cpu time.
+    ///
+    /// whether iowait and steal time are counted depends on the [`Accounting`] model. guest time
+    /// is part of user and nice time, so it is not counted again. states that were not reported
+    /// are not counted.
     pub fn active(&self) -> UserHz {
         let Self {
             user,
@@ -148,90 +204,345 @@ impl Measurement {
             irq,
             softirq,
             steal,
-            guest,
-            guest_nice,
-            idle: _, // do not count idle time...
+            guest: _,      // included in user time...
+            guest_nice: _, // included in nice time...
+            idle: _,       // do not count idle time...
+            trustworthy: _,
+            accounting,
         } = *self;
 
-        user + nice + system + iowait + irq + softirq + steal + guest + guest_nice
+        let iowait = iowait.filter(|_| accounting.iowait_busy());
+        let steal = steal.filter(|_| accounting.steal_busy());
+
+        [Some(user), Some(nice), Some(system)]
+            .into_iter()
+            .chain([iowait, irq, softirq, steal])
+            .flatten()
+            .sum()
     }
 
+    /// returns the total cpu time.
+    ///
+    /// guest time is part of user and nice time, so it is not counted again. states that were
+    /// not reported are not counted.
     pub fn total(&self) -> UserHz {
         let Self {
             user,
             nice,
             system,
+            idle,
             iowait,
             irq,
             softirq,
             steal,
-            guest,
-            guest_nice,
-            idle,
+            ..
         } = *self;
 
-        user + nice + system + iowait + irq + softirq + steal + guest + guest_nice + idle
+        [Some(user), Some(nice), Some(system), Some(idle)]
+            .into_iter()
+            .chain([iowait, irq, softirq, steal])
+            .flatten()
+            .sum()
     }
-}
 
-// === impl CpuTime ===
+    /// returns the amount of cpu time that was active, at the given tick rate.
+    ///
+    /// for an aggregate measurement this is the sum across all cpus, in cpu-seconds.
+    pub fn busy(&self, rate: TickRate) -> Duration {
+        self.active().as_duration(rate)
+    }
 
-impl TryFrom<Vec<UserHz>> for CpuTime {
-    type Error = EntryParseError;
-    fn try_from(times: Vec<UserHz>) -> Result<Self, Self::Error> {
-        <_ as TryInto<[_; 10]>>::try_into(times)
-            .map(Self::from)
-            .map_err(|_| EntryParseError::CpuTime)
+    /// returns the average number of cpus that were busy over the given wall-clock interval.
+    ///
+    /// returns `None` if `elapsed` is zero.
+    pub fn cores_busy(&self, rate: TickRate, elapsed: Duration) -> Option<f64> {
+        let elapsed = elapsed.as_secs_f64();
+        (elapsed > 0.0).then(|| self.busy(rate).as_secs_f64() / elapsed)
+    }
+
+    /// returns the time spent in user mode, including time spent running a guest.
+    pub fn user(&self) -> UserHz {
+        self.user
+    }
+
+    /// returns the time spent in user mode with low priority (nice), including time spent
+    /// running a niced guest.
+    pub fn nice(&self) -> UserHz {
+        self.nice
+    }
+
+    /// returns the time spent in system mode.
+    pub fn system(&self) -> UserHz {
+        self.system
+    }
+
+    /// returns the time spent in the idle task.
+    pub fn idle(&self) -> UserHz {
+        self.idle
+    }
+
+    /// returns the time waiting for i/o to complete, if it was reported.
+    pub fn iowait(&self) -> Option<UserHz> {
+        self.iowait
+    }
+
+    /// returns the time servicing interrupts, if it was reported.
+    pub fn irq(&self) -> Option<UserHz> {
+        self.irq
+    }
+
+    /// returns the time servicing softirqs, if it was reported.
+    pub fn softirq(&self) -> Option<UserHz> {
+        self.softirq
+    }
+
+    /// returns the stolen time, if it was reported.
+    pub fn steal(&self) -> Option<UserHz> {
+        self.steal
+    }
+
+    /// returns the time spent running a guest, if it was reported.
+    pub fn guest(&self) -> Option<UserHz> {
+        self.guest
+    }
+
+    /// returns the time spent running a niced guest, if it was reported.
+    pub fn guest_nice(&self) -> Option<UserHz> {
+        self.guest_nice
+    }
+
+    /// returns the time spent in the given state, if it was reported.
+    pub fn time(&self, state: State) -> Option<UserHz> {
+        match state {
+            State::User => Some(self.user),
+            State::Nice => Some(self.nice),
+            State::System => Some(self.system),
+            State::Idle => Some(self.idle),
+            State::Iowait => self.iowait,
+            State::Irq => self.irq,
+            State::Softirq => self.softirq,
+            State::Steal => self.steal,
+            State::Guest => self.guest,
+            State::GuestNice => self.guest_nice,
+        }
+    }
+
+    /// returns the time spent in the given state, excluding time counted by another state.
+    ///
+    /// user and nice time include guest time, which is subtracted here, as `mpstat` does.
+    pub fn exclusive_time(&self, state: State) -> Option<UserHz> {
+        let time = self.time(state)?;
+        let guest = match state {
+            State::User => self.guest,
+            State::Nice => self.guest_nice,
+            _ => None,
+        };
+
+        Some(guest.map_or(time, |guest| time - guest))
+    }
+
+    /// returns the fraction of the total cpu time spent in the given state, from zero to one.
+    ///
+    /// user and nice time exclude guest time, so that the fractions of every state add up to one.
+    /// returns `None` if the state was not reported, or if no time passed.
+    pub fn fraction(&self, state: State) -> Option<f64> {
+        let total = self.total();
+        let time = self.exclusive_time(state)?;
+        (total.get() > 0).then(|| time / total)
+    }
+
+    /// returns the fraction of the total cpu time spent in user mode, excluding guest time.
+    pub fn user_fraction(&self) -> Option<f64> {
+        self.fraction(State::User)
+    }
+
+    /// returns the fraction of the total cpu time spent in user mode with low priority, excluding
+    /// niced guest time.
+    pub fn nice_fraction(&self) -> Option<f64> {
+        self.fraction(State::Nice)
+    }
+
+    /// returns the fraction of the total cpu time spent in system mode.
+    pub fn system_fraction(&self) -> Option<f64> {
+        self.fraction(State::System)
+    }
+
+    /// returns the fraction of the total cpu time spent in the idle task.
+    pub fn idle_fraction(&self) -> Option<f64> {
+        self.fraction(State::Idle)
+    }
+
+    /// returns the fraction of the total cpu time spent waiting for i/o to complete.
+    pub fn iowait_fraction(&self) -> Option<f64> {
+        self.fraction(State::Iowait)
+    }
+
+    /// returns the fraction of the total cpu time spent servicing interrupts.
+    pub fn irq_fraction(&self) -> Option<f64> {
+        self.fraction(State::Irq)
+    }
+
+    /// returns the fraction of the total cpu time spent servicing softirqs.
+    pub fn softirq_fraction(&self) -> Option<f64> {
+        self.fraction(State::Softirq)
+    }
+
+    /// returns the fraction of the total cpu time that was stolen.
+    pub fn steal_fraction(&self) -> Option<f64> {
+        self.fraction(State::Steal)
+    }
+
+    /// returns the fraction of the total cpu time spent running a guest.
+    pub fn guest_fraction(&self) -> Option<f64> {
+        self.fraction(State::Guest)
+    }
+
+    /// returns the fraction of the total cpu time spent running a niced guest.
+    pub fn guest_nice_fraction(&self) -> Option<f64> {
+        self.fraction(State::GuestNice)
     }
 }
 
-impl From<[UserHz; 10]> for CpuTime {
-    fn from(
-        [
-            user,
-            nice,
-            system,
-            idle,
-            iowait,
-            irq,
-            softirq,
-            steal,
-            guest,
-            guest_nice,
-        ]: [UserHz; 10],
-    ) -> Self {
-        Self {
-            user,
-            nice,
-            system,
-            idle,
-            iowait,
-            irq,
-            softirq,
-            steal,
-            guest,
-            guest_nice,
+// === impl State ===
+
+impl State {
+    /// every state, in the order that the kernel reports them.
+    pub const ALL: [Self; 10] = [
+        Self::User,
+        Self::Nice,
+        Self::System,
+        Self::Idle,
+        Self::Iowait,
+        Self::Irq,
+        Self::Softirq,
+        Self::Steal,
+        Self::Guest,
+        Self::GuestNice,
+    ];
+
+    /// returns the name of this state, as used in `/proc/stat`'s documentation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Nice => "nice",
+            Self::System => "system",
+            Self::Idle => "idle",
+            Self::Iowait => "iowait",
+            Self::Irq => "irq",
+            Self::Softirq => "softirq",
+            Self::Steal => "steal",
+            Self::Guest => "guest",
+            Self::GuestNice => "guest_nice",
         }
     }
 }
 
-impl Into<[UserHz; 10]> for CpuTime {
-    fn into(self) -> [UserHz; 10] {
-        let Self {
+impl Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// === impl CpuTime ===
+
+impl CpuTime {
+    /// the number of columns that are always present.
+    ///
+    /// user, nice, system, and idle time have been reported since the earliest 2.x kernels.
+    const REQUIRED: usize = 4;
+
+    /// returns the time spent in user mode.
+    pub fn user(&self) -> UserHz {
+        self.user
+    }
+
+    /// returns the time spent in user mode with low priority (nice).
+    pub fn nice(&self) -> UserHz {
+        self.nice
+    }
+
+    /// returns the time spent in system mode.
+    pub fn system(&self) -> UserHz {
+        self.system
+    }
+
+    /// returns the time spent in the idle task.
+    pub fn idle(&self) -> UserHz {
+        self.idle
+    }
+
+    /// returns the time waiting for i/o to complete, if it was reported.
+    pub fn iowait(&self) -> Option<UserHz> {
+        self.iowait
+    }
+
+    /// returns the time servicing interrupts, if it was reported.
+    pub fn irq(&self) -> Option<UserHz> {
+        self.irq
+    }
+
+    /// returns the time servicing softirqs, if it was reported.
+    pub fn softirq(&self) -> Option<UserHz> {
+        self.softirq
+    }
+
+    /// returns the stolen time, if it was reported.
+    pub fn steal(&self) -> Option<UserHz> {
+        self.steal
+    }
+
+    /// returns the time spent running a guest, if it was reported.
+    pub fn guest(&self) -> Option<UserHz> {
+        self.guest
+    }
+
+    /// returns the time spent running a niced guest, if it was reported.
+    pub fn guest_nice(&self) -> Option<UserHz> {
+        self.guest_nice
+    }
+
+    /// returns the number of time columns that the kernel reported, up to ten.
+    pub fn columns(&self) -> usize {
+        let optional = [
+            self.iowait,
+            self.irq,
+            self.softirq,
+            self.steal,
+            self.guest,
+            self.guest_nice,
+        ];
+
+        Self::REQUIRED + optional.iter().take_while(|t| t.is_some()).count()
+    }
+}
+
+/// parses a cpu's times from a list of four to ten columns.
+///
+/// columns after the tenth, guest_nice, are ignored so that times can be read from kernels that
+/// report additional states.
+impl TryFrom<Vec<UserHz>> for CpuTime {
+    type Error = EntryParseError;
+    fn try_from(times: Vec<UserHz>) -> Result<Self, Self::Error> {
+        let columns = times.len();
+        let (required, optional) = match times.split_at_checked(Self::REQUIRED) {
+            Some(split) => split,
+            None => return Err(EntryParseError::CpuTime { columns }),
+        };
+
+        let [user, nice, system, idle] = required.try_into().expect("four required columns");
+        let optional = |i: usize| optional.get(i).copied();
+
+        Ok(Self {
             user,
             nice,
             system,
             idle,
-            iowait,
-            irq,
-            softirq,
-            steal,
-            guest,
-            guest_nice,
-        } = self;
-
-        [
-            user, nice, system, idle, iowait, irq, softirq, steal, guest, guest_nice,
-        ]
+            iowait: optional(0),
+            irq: optional(1),
+            softirq: optional(2),
+            steal: optional(3),
+            guest: optional(4),
+            guest_nice: optional(5),
+        })
     }
 }
@@ -23,6 +23,12 @@ mod entry_parse_tests {
         let _ = EXAMPLE_3.parse::<Entry>().unwrap();
     }
 
+    #[test]
+    fn empty() {
+        let err = "".parse::<Entry>().unwrap_err();
+        assert_eq!(err, EntryParseError::Empty);
+    }
+
     #[test]
     fn bad_cpu_id() {
         let err = "cpuA 0 0 0 0 0 0 0 0 0 0".parse::<Entry>().unwrap_err();
@@ -39,62 +45,106 @@ mod entry_parse_tests {
         }
     }
 
-    /// parse a cpu entry that is missing one of its times.
+    /// parse a cpu entry that is missing its guest_nice time, as kernels before 2.6.33 do.
     #[test]
     fn missing_time() {
         const ENTRY: &str = "cpu 10132153 290696 3084719 46828483 16683 0 25195 0 175628";
+        let Entry::AllCpu { time } = ENTRY.parse::<Entry>().unwrap() else {
+            panic!()
+        };
+        assert_eq!(time.columns(), 9);
+    }
+
+    /// parse a cpu entry with only the seven columns reported by kernels before 2.6.11.
+    #[test]
+    fn seven_times() {
+        const ENTRY: &str = "cpu0 1393280 32966 572056 13343292 6130 0 17875";
+        let Entry::Cpu { time, .. } = ENTRY.parse::<Entry>().unwrap() else {
+            panic!()
+        };
+        assert_eq!(time.columns(), 7);
+    }
+
+    /// parse a cpu entry with only the four required columns.
+    #[test]
+    fn four_times() {
+        const ENTRY: &str = "cpu0 1393280 32966 572056 13343292";
+        let Entry::Cpu { time, .. } = ENTRY.parse::<Entry>().unwrap() else {
+            panic!()
+        };
+        assert_eq!(time.columns(), 4);
+    }
+
+    /// parse a cpu entry with too few columns.
+    #[test]
+    fn three_times() {
+        const ENTRY: &str = "cpu0 1393280 32966 572056";
         let err = ENTRY.parse::<Entry>().unwrap_err();
-        assert_eq!(err, EntryParseError::CpuTime);
+        assert_eq!(err, EntryParseError::CpuTime { columns: 3 });
     }
 
-    /// parse a cpu entry that has one too many times..
+    /// parse a cpu entry that has one too many times, as a future kernel might.
     #[test]
     fn extra_time() {
         const ENTRY: &str = "cpu 10132153 290696 3084719 46828483 16683 0 25195 0 175628 0 0";
-        let err = ENTRY.parse::<Entry>().unwrap_err();
-        assert_eq!(err, EntryParseError::CpuTime);
+        let Entry::AllCpu { time } = ENTRY.parse::<Entry>().unwrap() else {
+            panic!()
+        };
+        assert_eq!(time.columns(), 10);
     }
 
     #[test]
     fn page() {
         let entry = "page 5741 1808".parse::<Entry>().unwrap();
-        assert_eq!(entry, Entry::Page);
+        assert_eq!(
+            entry,
+            Entry::Page {
+                paged_in: 5741,
+                paged_out: 1808
+            }
+        );
     }
 
     #[test]
     fn swap() {
         let entry = "swap 1 0".parse::<Entry>().unwrap();
-        assert_eq!(entry, Entry::Swap);
+        assert_eq!(
+            entry,
+            Entry::Swap {
+                swapped_in: 1,
+                swapped_out: 0
+            }
+        );
     }
 
     #[test]
     fn intr() {
         let entry = "intr 1462898".parse::<Entry>().unwrap();
-        assert_eq!(entry, Entry::Intr);
+        assert_eq!(entry, Entry::Intr { total: 1462898 });
     }
 
     #[test]
     fn btime() {
         let entry = "btime 769041601".parse::<Entry>().unwrap();
-        assert_eq!(entry, Entry::Btime);
+        assert_eq!(entry, Entry::Btime { seconds: 769041601 });
     }
 
     #[test]
     fn processes() {
         let entry = "processes 86031".parse::<Entry>().unwrap();
-        assert_eq!(entry, Entry::Processes);
+        assert_eq!(entry, Entry::Processes { forks: 86031 });
     }
 
     #[test]
     fn procs_running() {
         let entry = "procs_running 6".parse::<Entry>().unwrap();
-        assert_eq!(entry, Entry::ProcsRunning);
+        assert_eq!(entry, Entry::ProcsRunning { count: 6 });
     }
 
     #[test]
     fn procs_blocked() {
         let entry = "procs_blocked 2".parse::<Entry>().unwrap();
-        assert_eq!(entry, Entry::ProcsBlocked);
+        assert_eq!(entry, Entry::ProcsBlocked { count: 2 });
     }
 
     #[test]
@@ -103,16 +153,52 @@ mod entry_parse_tests {
             "softirq 229245889 94 60001584 13619 5175704 2471304 28 51212741 59130143 0 51240672"
                 .parse::<Entry>()
                 .unwrap();
-        assert_eq!(entry, Entry::SoftIrq);
+        assert_eq!(entry, Entry::SoftIrq { total: 229245889 });
+    }
+
+    /// parse an interrupt entry with per-interrupt columns following the total.
+    #[test]
+    fn intr_with_columns() {
+        let entry = "intr 1462898 0 9 0 0 0 0 3 0 1 0".parse::<Entry>().unwrap();
+        assert_eq!(entry, Entry::Intr { total: 1462898 });
+    }
+
+    #[test]
+    fn big_cpu_id() {
+        let entry = "cpu383 1393280 32966 572056 13343292 6130 0 17875 0 23933 0"
+            .parse::<Entry>()
+            .unwrap();
+        assert!(matches!(entry, Entry::Cpu { id: CpuId(383), .. }));
     }
 
     #[test]
-    #[ignore = "TODO"]
+    fn ctxt() {
+        let entry = "ctxt 115315".parse::<Entry>().unwrap();
+        assert_eq!(entry, Entry::Ctxt { switches: 115315 });
+    }
+
+    #[test]
+    fn missing_counter() {
+        let err = "page 5741".parse::<Entry>().unwrap_err();
+        match err {
+            EntryParseError::MissingCounter { kind } if kind == "page" => {}
+            _other => panic!(),
+        }
+    }
+
+    #[test]
+    fn bad_counter() {
+        let err = "ctxt abc".parse::<Entry>().unwrap_err();
+        assert!(matches!(err, EntryParseError::CounterParse(_)));
+    }
+
+    /// parse an aggregate cpu entry whose counters exceed `u32::MAX`.
+    #[test]
     fn big() {
         let entry = "cpu  5000000000 5000000000 5000000000 5000000000 5000000000 5000000000 5000000000 0 0 0"
                 .parse::<Entry>()
                 .unwrap();
-        assert_eq!(entry, Entry::SoftIrq);
+        assert!(matches!(entry, Entry::AllCpu { .. }));
     }
 }
 
@@ -134,6 +220,28 @@ mod parse_cpu_id_tests {
         assert_eq!(Entry::parse_cpu_id("cpu2"), Ok(Some(CpuId(2))));
     }
 
+    #[test]
+    fn two_hundred_fifty_six() {
+        assert_eq!(Entry::parse_cpu_id("cpu256"), Ok(Some(CpuId(256))));
+    }
+
+    #[test]
+    fn max() {
+        assert_eq!(Entry::parse_cpu_id("cpu8191"), Ok(Some(CpuId(8191))));
+    }
+
+    #[test]
+    fn ordering() {
+        let (nine, ten) = (CpuId(9), CpuId(10));
+        assert!(nine < ten);
+        assert!(CpuId(255) < CpuId(256));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(CpuId(511).to_string(), "cpu511");
+    }
+
     #[test]
     fn a() {
         assert!(matches!(
@@ -142,3 +250,321 @@ mod parse_cpu_id_tests {
         ));
     }
 }
+
+mod rates_tests {
+    use {super::*, std::time::Duration};
+
+    fn counters(context_switches: u64, forks: u64, procs_running: u64) -> Counters {
+        let mut counters = Counters::default();
+        for line in [
+            format!("ctxt {context_switches}"),
+            format!("processes {forks}"),
+            format!("procs_running {procs_running}"),
+        ] {
+            counters.insert(&line.parse::<Entry>().unwrap());
+        }
+        counters
+    }
+
+    #[test]
+    fn per_second() {
+        let (a, b) = (counters(100, 10, 1), counters(300, 14, 3));
+        let rates = Rates::new(a, b, Duration::from_secs(2));
+        assert_eq!(rates.context_switches(), Some(100.0));
+        assert_eq!(rates.forks(), Some(2.0));
+        assert_eq!(rates.procs_running(), Some(3));
+    }
+
+    #[test]
+    fn unreported() {
+        let rates = Rates::new(
+            Counters::default(),
+            counters(300, 14, 3),
+            Duration::from_secs(1),
+        );
+        assert_eq!(rates.context_switches(), None);
+        assert_eq!(rates.interrupts(), None);
+    }
+
+    #[test]
+    fn decreasing() {
+        let (a, b) = (counters(300, 10, 1), counters(100, 14, 3));
+        let rates = Rates::new(a, b, Duration::from_secs(1));
+        assert_eq!(rates.context_switches(), None);
+        assert_eq!(rates.forks(), Some(4.0));
+    }
+
+    #[test]
+    fn insert() {
+        let mut counters = Counters::default();
+        for line in ["ctxt 115315", "procs_blocked 2", "swap 1 0"] {
+            counters.insert(&line.parse::<Entry>().unwrap());
+        }
+        assert_eq!(counters.context_switches(), Some(115315));
+        assert_eq!(counters.procs_blocked(), Some(2));
+        assert_eq!(counters.swapped_in(), Some(1));
+        assert_eq!(counters.forks(), None);
+    }
+}
+
+mod measurement_tests {
+    use super::*;
+
+    fn time(line: &str) -> CpuTime {
+        match line.parse::<Entry>().unwrap() {
+            Entry::AllCpu { time } | Entry::Cpu { time, .. } => time,
+            _other => panic!(),
+        }
+    }
+
+    /// iowait is counted as idle time, by default.
+    #[test]
+    fn ten_columns() {
+        let a = time("cpu 10 0 10 70 10 0 0 0 0 0");
+        let b = time("cpu 20 0 20 140 20 0 0 0 0 0");
+        let measurement = Measurement::new(a, b);
+        assert_eq!(measurement.iowait(), Some("10".parse().unwrap()));
+        assert_eq!(measurement.percentage(), 20);
+    }
+
+    /// guest time is already counted as user and nice time.
+    #[test]
+    fn guest_is_not_double_counted() {
+        let a = time("cpu 0 0 0 0 0 0 0 0 0 0");
+        let b = time("cpu 60 20 0 20 0 0 0 0 40 20");
+        let measurement = Measurement::new(a, b);
+        assert_eq!(measurement.total(), "100".parse().unwrap());
+        assert_eq!(measurement.percentage(), 80);
+        assert_eq!(measurement.user_fraction(), Some(0.2));
+        assert_eq!(measurement.guest_fraction(), Some(0.4));
+        assert_eq!(measurement.nice_fraction(), Some(0.0));
+        assert_eq!(measurement.guest_nice_fraction(), Some(0.2));
+    }
+
+    #[test]
+    fn accounting() {
+        let a = time("cpu 0 0 0 0 0 0 0 0 0 0");
+        let b = time("cpu 20 0 0 40 20 0 0 20 0 0");
+        let measurement =
+            |accounting| Measurement::new(a.clone(), b.clone()).with_accounting(accounting);
+
+        let default = Accounting::default();
+        assert_eq!(measurement(default).percentage(), 40);
+        assert_eq!(measurement(default.with_iowait_busy(true)).percentage(), 60);
+        assert_eq!(measurement(default.with_steal_busy(false)).percentage(), 20);
+        assert_eq!(
+            measurement(default.with_iowait_busy(true).with_steal_busy(false)).total(),
+            "100".parse().unwrap()
+        );
+    }
+
+    /// states that were not reported are absent, and are not counted.
+    #[test]
+    fn four_columns() {
+        let a = time("cpu 10 0 10 80");
+        let b = time("cpu 20 0 20 160");
+        let measurement = Measurement::new(a, b);
+        assert_eq!(measurement.iowait(), None);
+        assert_eq!(measurement.steal(), None);
+        assert_eq!(measurement.total(), "100".parse().unwrap());
+        assert_eq!(measurement.percentage(), 20);
+    }
+
+    /// states that were only reported by one of the two times are absent.
+    #[test]
+    fn mismatched_columns() {
+        let a = time("cpu 10 0 10 80 0 0 0");
+        let b = time("cpu 20 0 20 160 0 0 0 5");
+        let measurement = Measurement::new(a, b);
+        assert_eq!(measurement.irq(), Some("0".parse().unwrap()));
+        assert_eq!(measurement.steal(), None);
+    }
+
+    #[test]
+    fn fractions() {
+        let a = time("cpu 0 0 0 0 0 0 0 0 0 0");
+        let b = time("cpu 40 10 20 20 10 0 0 0 0 0");
+        let measurement = Measurement::new(a, b);
+        assert_eq!(measurement.user_fraction(), Some(0.4));
+        assert_eq!(measurement.nice_fraction(), Some(0.1));
+        assert_eq!(measurement.system_fraction(), Some(0.2));
+        assert_eq!(measurement.idle_fraction(), Some(0.2));
+        assert_eq!(measurement.iowait_fraction(), Some(0.1));
+        assert_eq!(measurement.steal_fraction(), Some(0.0));
+        let sum: f64 = State::ALL
+            .iter()
+            .filter_map(|state| measurement.fraction(*state))
+            .sum();
+        assert!((sum - 1.0).abs() < 1e-9);
+    }
+
+    /// states that were not reported have no fraction.
+    #[test]
+    fn unreported_fractions() {
+        let a = time("cpu 10 0 10 80");
+        let b = time("cpu 20 0 20 160");
+        let measurement = Measurement::new(a, b);
+        assert_eq!(measurement.user_fraction(), Some(0.1));
+        assert_eq!(measurement.iowait_fraction(), None);
+        assert_eq!(measurement.guest_nice_fraction(), None);
+    }
+
+    /// no time passed, so there is nothing to divide.
+    #[test]
+    fn idle_interval_fractions() {
+        let a = time("cpu 10 0 10 80");
+        let measurement = Measurement::new(a.clone(), a);
+        assert_eq!(measurement.user_fraction(), None);
+    }
+
+    #[test]
+    fn trustworthy() {
+        let a = time("cpu 10 0 10 70 10 0 0 0 0 0");
+        let b = time("cpu 20 0 20 140 20 0 0 0 0 0");
+        assert!(Measurement::new(a, b).is_trustworthy());
+    }
+
+    /// a counter that goes backwards is clamped to zero, rather than panicking.
+    #[test]
+    fn decreasing_iowait() {
+        let a = time("cpu 10 0 10 70 20 0 0 0 0 0");
+        let b = time("cpu 20 0 20 140 10 0 0 0 0 0");
+        let measurement = Measurement::new(a, b);
+        assert!(!measurement.is_trustworthy());
+        assert_eq!(measurement.iowait(), Some("0".parse().unwrap()));
+        assert_eq!(measurement.total(), "90".parse().unwrap());
+    }
+
+    #[test]
+    fn ratio() {
+        let a = time("cpu 0 0 0 0");
+        let b = time("cpu 1 0 0 2");
+        let measurement = Measurement::new(a, b);
+        let ratio = measurement.ratio().unwrap();
+        assert!((ratio - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(measurement.percentage(), 33);
+        assert_eq!(measurement.normalized(1000), 255);
+    }
+
+    /// no ticks passed during the interval, so there is no ratio.
+    #[test]
+    fn zero_ticks() {
+        let a = time("cpu 10 0 10 80 0 0 0 0 0 0");
+        let measurement = Measurement::new(a.clone(), a);
+        assert_eq!(measurement.ratio(), None);
+        assert_eq!(measurement.percentage(), 0);
+        assert_eq!(measurement.normalized(8), 0);
+    }
+
+    /// counters past `u32::MAX` are measured correctly.
+    #[test]
+    fn big() {
+        let a = time("cpu 5000000000 0 0 5000000000");
+        let b = time("cpu 5000000100 0 0 5000000100");
+        let measurement = Measurement::new(a, b);
+        assert!(measurement.is_trustworthy());
+        assert_eq!(measurement.percentage(), 50);
+    }
+}
+
+mod tick_rate_tests {
+    use {super::*, std::time::Duration};
+
+    fn ticks(n: u64) -> UserHz {
+        n.to_string().parse().unwrap()
+    }
+
+    #[test]
+    fn one_second() {
+        let rate = TickRate::new(100).unwrap();
+        assert_eq!(ticks(100).as_duration(rate), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn fractional() {
+        let rate = TickRate::new(100).unwrap();
+        assert_eq!(ticks(150).as_duration(rate), Duration::from_millis(1500));
+        let rate = TickRate::new(250).unwrap();
+        assert_eq!(ticks(1).as_duration(rate), Duration::from_millis(4));
+    }
+
+    #[test]
+    fn big() {
+        let rate = TickRate::new(1000).unwrap();
+        let duration = ticks(u64::MAX).as_duration(rate);
+        assert_eq!(duration.as_secs(), u64::MAX / 1000);
+    }
+
+    /// the fraction of a second is not overflowed by a very high tick rate.
+    #[test]
+    fn big_rate() {
+        let rate = TickRate::new(100_000_000_000).unwrap();
+        let duration = ticks(u64::MAX).as_duration(rate);
+        assert_eq!(duration, Duration::new(184_467_440, 737_095_516));
+    }
+
+    #[test]
+    fn zero() {
+        assert_eq!(TickRate::new(0), None);
+    }
+
+    #[test]
+    fn query() {
+        assert!(TickRate::query().get() > 0);
+    }
+
+    /// two cpus, each fully busy for one second, over a one second interval.
+    #[test]
+    fn cores_busy() {
+        let time = |line: &str| match line.parse::<Entry>().unwrap() {
+            Entry::AllCpu { time } => time,
+            _other => panic!(),
+        };
+        let a = time("cpu 0 0 0 0");
+        let b = time("cpu 200 0 0 0");
+        let measurement = Measurement::new(a, b);
+        let rate = TickRate::new(100).unwrap();
+        assert_eq!(measurement.busy(rate), Duration::from_secs(2));
+        assert_eq!(
+            measurement.cores_busy(rate, Duration::from_secs(1)),
+            Some(2.0)
+        );
+        assert_eq!(measurement.cores_busy(rate, Duration::ZERO), None);
+    }
+}
+
+mod snapshot_tests {
+    use {
+        super::*,
+        crate::source::mock::{MockStatClock, MockStatFile},
+        std::time::Duration,
+    };
+
+    fn read(stats: &str) -> Result<Snapshot, StatReadError> {
+        let clock = MockStatClock::ticking(Duration::from_secs(1), 1);
+        Snapshot::read(&MockStatFile::new([stats]), &clock)
+    }
+
+    #[test]
+    fn read_snapshot() {
+        let snapshot = read("cpu 1 2 3 4\ncpu0 1 2 3 4\nctxt 5\n").unwrap();
+        assert_eq!(snapshot.system().user(), UserHz::new(1));
+        assert_eq!(snapshot.cpus().len(), 1);
+        assert_eq!(snapshot.counters().context_switches(), Some(5));
+    }
+
+    #[test]
+    fn missing_system() {
+        let err = read("cpu0 1 2 3 4\n").unwrap_err();
+        assert!(matches!(err, StatReadError::MissingSystem));
+    }
+
+    #[test]
+    fn bad_entry() {
+        let err = read("cpu 1 2 3 4\nwrong 1\n").unwrap_err();
+        assert!(matches!(
+            err,
+            StatReadError::Entry(EntryParseError::UnrecognizedEntry { .. })
+        ));
+    }
+}
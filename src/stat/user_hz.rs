@@ -1,24 +1,57 @@
 use std::{
+    iter::Sum,
+    num::NonZeroU64,
     ops::{Add, Div, Sub},
     str::FromStr,
+    time::Duration,
 };
 
+/// an amount of time, measured in clock ticks.
+///
+/// the kernel reports these as 64-bit counters, which will not wrap around in practice.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct UserHz(u64);
+
+/// the number of clock ticks in a second.
+///
+/// this can be obtained via `getconf(1)` and `CLK_TCK`, or `sysconf(_SC_CLK_TCK)`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct UserHz(u32);
+pub struct TickRate(NonZeroU64);
 
 // === impl UserHz ===
 
 impl UserHz {
-    /// the number of clock ticks in a second.
+    /// returns the given number of clock ticks.
+    pub const fn new(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    /// returns the number of clock ticks.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+
+    /// returns the amount of time that these ticks represent, at the given tick rate.
+    pub fn as_duration(self, TickRate(hz): TickRate) -> Duration {
+        let (Self(ticks), hz) = (self, hz.get());
+        let secs = ticks / hz;
+        // the remainder is scaled in 128 bits, so that very high tick rates cannot overflow. the
+        // result is less than a second, so it fits in 64 bits.
+        let nanos = (u128::from(ticks % hz) * 1_000_000_000 / u128::from(hz)) as u64;
+        Duration::from_secs(secs) + Duration::from_nanos(nanos)
+    }
+
+    /// subtracts `rhs` from this counter, returning `None` if the counter went backwards.
     ///
-    /// this can be obtained via `getconf(1)` and `CLK_TCK`, or `sysconf(_SC_CLK_TCK)`. usually,
-    /// this is 100Hz, so it is hard-coded for now.
-    #[allow(unused, reason = "prototyping")]
-    const FREQ: u8 = 100;
+    /// counters should only grow, but some (like iowait) may decrease in certain conditions.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        let (Self(lhs), Self(rhs)) = (self, rhs);
+        lhs.checked_sub(rhs).map(Self)
+    }
 }
 
 impl FromStr for UserHz {
-    type Err = <u128 as FromStr>::Err;
+    type Err = <u64 as FromStr>::Err;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         s.parse().map(Self)
     }
@@ -32,20 +65,66 @@ impl Add for UserHz {
     }
 }
 
+impl Sum for UserHz {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self(0), Add::add)
+    }
+}
+
+/// subtracts two amounts of time, saturating at zero.
+///
+/// see [`UserHz::checked_sub`] to detect counters that went backwards.
 impl Sub for UserHz {
     type Output = Self;
     fn sub(self, rhs: Self) -> Self::Output {
         let (Self(lhs), Self(rhs)) = (self, rhs);
-        Self(lhs - rhs)
+        Self(lhs.saturating_sub(rhs))
     }
 }
 
 impl Div for UserHz {
     type Output = f64;
     fn div(self, rhs: Self) -> Self::Output {
-        let to_float = |Self(hz)| -> f64 { hz.try_into().unwrap() };
+        let to_float = |Self(hz)| -> f64 { hz as f64 };
         let (lhs, rhs) = (to_float(self), to_float(rhs));
 
         lhs / rhs
     }
 }
+
+// === impl TickRate ===
+
+impl TickRate {
+    /// the tick rate used when it cannot be queried.
+    ///
+    /// linux reports times to userspace at 100Hz on nearly every architecture.
+    pub const FALLBACK: Self = Self(NonZeroU64::new(100).unwrap());
+
+    /// returns a tick rate of `hz` ticks per second, or `None` if `hz` is zero.
+    pub fn new(hz: u64) -> Option<Self> {
+        NonZeroU64::new(hz).map(Self)
+    }
+
+    /// queries the system's tick rate via `sysconf(_SC_CLK_TCK)`.
+    ///
+    /// this falls back to [`TickRate::FALLBACK`] if the tick rate cannot be queried.
+    pub fn query() -> Self {
+        // SAFETY: `sysconf` has no preconditions, and returns -1 if the name is not supported.
+        let hz = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        u64::try_from(hz)
+            .ok()
+            .and_then(Self::new)
+            .unwrap_or(Self::FALLBACK)
+    }
+
+    /// returns the number of ticks in a second.
+    pub fn get(&self) -> u64 {
+        self.0.get()
+    }
+}
+
+impl Default for TickRate {
+    fn default() -> Self {
+        Self::query()
+    }
+}
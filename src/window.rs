@@ -1,87 +1,580 @@
 use {
+    self::{
+        screen::{Cell, Screen},
+        terminal::Terminal,
+    },
     super::*,
+    crate::{
+        meter::{Align, Meter, Sparkline},
+        stat::{CpuId, Measurement, Rates, State},
+    },
     crossterm::{
-        ExecutableCommand, QueueableCommand, cursor,
-        style::{self, Stylize},
-        terminal,
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        style::Color,
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet, VecDeque},
+        time::Instant,
     },
-    std::collections::VecDeque,
 };
 
-impl App {
-    pub fn tui(self) -> Result<(), crate::Error> {
-        let Self { mut sentinel } = self;
+mod screen;
+mod terminal;
+
+#[cfg(test)]
+mod tests;
+
+/// what the tui should do after waiting for the next sample.
+enum Flow {
+    /// take another sample.
+    Continue,
+    /// the terminal was resized to the given number of columns and rows.
+    Resize(u16, u16),
+    /// switch to the next view.
+    NextView,
+    /// scroll the list of cpus.
+    Scroll(Scroll),
+    /// exit the tui.
+    Quit,
+}
+
+/// the contents of the tui window.
+struct Window {
+    /// the frame being drawn.
+    screen: Screen,
+    /// each cpu's recent measurements, oldest first.
+    history: VecDeque<BTreeMap<CpuId, Measurement>>,
+    /// every cpu that has been seen, including those that have since gone offline.
+    known: BTreeSet<CpuId>,
+    /// the most recent recording.
+    latest: Option<Recording>,
+    /// the width of each cpu's meter.
+    meter_width: usize,
+    /// where the filled part of each cpu's meter is placed.
+    meter_align: Align,
+    /// how each cpu is drawn.
+    view: View,
+    /// the position of the first cpu shown, when there are more cpus than rows.
+    scroll: usize,
+}
+
+/// how to scroll the list of cpus.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Scroll {
+    /// up by one cpu.
+    Up,
+    /// down by one cpu.
+    Down,
+    /// up by a screenful of cpus.
+    PageUp,
+    /// down by a screenful of cpus.
+    PageDown,
+    /// to the first cpu.
+    Top,
+    /// to the last cpu.
+    Bottom,
+}
+
+/// how each cpu is drawn.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum View {
+    /// a meter of the cpu's current load, and a sparkline of its recent history.
+    #[default]
+    Sparklines,
+    /// a bar, divided into the share of time spent in each state.
+    Breakdown,
+}
 
-        Self::clear()?;
+impl App {
+    /// runs the tui, drawing each cpu's load until the user quits or the requested number of
+    /// samples have been taken.
+    ///
+    /// the terminal is restored when this returns, or if the application panics.
+    pub(crate) fn tui(mut self) -> Result<(), crate::Error> {
+        let _terminal = Terminal::enter()?;
 
         let (cols, rows) = crossterm::terminal::size()?;
+        let mut window = Window::new(cols, rows, &self.options);
+        let mut stdout = io::stdout();
 
-        let mut recordings = VecDeque::new();
-        loop {
-            Self::border(cols, rows)?;
-
-            if let Some(Recording {
-                start: _,
-                end: _,
-                system: _,
-                cpus,
-            }) = sentinel.observe()?
-            {
-                for (cpu, _) in cpus.iter() {
-                    io::stdout()
-                        .queue(cursor::MoveTo(((cpu.as_u16() * 10) + 2) as u16, 2))?
-                        .queue(style::PrintStyledContent(
-                            format!("cpu{}", cpu.as_u16()).grey(),
-                        ))?;
-                }
+        let mut taken = 0;
+        while !self.finished(taken) {
+            let deadline = Instant::now() + self.options.interval;
+
+            if let Some(recording) = self.observe()? {
+                taken += 1;
+                window.record(recording);
+            }
+
+            window.draw(&mut stdout)?;
+            if self.finished(taken) {
+                break;
+            }
 
-                recordings.push_back(cpus);
-                if recordings.len() > (rows - 6) as usize {
-                    recordings.pop_front();
+            loop {
+                match self.wait(deadline)? {
+                    Flow::Continue => break,
+                    Flow::Resize(cols, rows) => {
+                        window.resize(cols, rows);
+                        window.redraw(&mut stdout)?;
+                    }
+                    Flow::NextView => {
+                        window.next_view();
+                        window.draw(&mut stdout)?;
+                    }
+                    Flow::Scroll(scroll) => {
+                        window.scroll(scroll);
+                        window.draw(&mut stdout)?;
+                    }
+                    Flow::Quit => return Ok(()),
                 }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// waits until `deadline`, handling input and signals in the meantime.
+    ///
+    /// this returns early if the terminal is resized, so that the window can be redrawn.
+    fn wait(&self, deadline: Instant) -> io::Result<Flow> {
+        loop {
+            if self.shutdown_requested() {
+                return Ok(Flow::Quit);
+            }
+
+            let Some(timeout) = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|timeout| !timeout.is_zero())
+            else {
+                return Ok(Flow::Continue);
+            };
+
+            // wake up periodically, to notice signals.
+            if !event::poll(timeout.min(Self::TICK))? {
+                continue;
+            }
 
-                for (row, r) in recordings.iter().enumerate() {
-                    for (cpu, measurement) in r.iter() {
-                        io::stdout()
-                            .queue(cursor::MoveTo(
-                                ((cpu.as_u16() * 10) + 2) as u16,
-                                (row + 4) as u16,
-                            ))?
-                            .queue(style::PrintStyledContent(
-                                format!("{}", measurement.percentage(),).green(),
-                            ))?;
+            match event::read()? {
+                Event::Key(key) if Self::is_quit(&key) => return Ok(Flow::Quit),
+                Event::Key(key) if Self::is_next_view(&key) => return Ok(Flow::NextView),
+                Event::Key(key) => {
+                    if let Some(scroll) = Self::scroll(&key) {
+                        return Ok(Flow::Scroll(scroll));
                     }
                 }
+                Event::Resize(cols, rows) => return Ok(Flow::Resize(cols, rows)),
+                _ => {}
             }
+        }
+    }
 
-            io::stdout().queue(cursor::Hide)?;
+    /// returns `true` if the key press should exit the tui.
+    ///
+    /// `q`, escape, and ctrl-c each exit the tui.
+    fn is_quit(key: &KeyEvent) -> bool {
+        if key.kind != KeyEventKind::Press {
+            return false;
+        }
 
-            io::stdout().flush()?;
-            Self::sleep();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => true,
+            KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+            _ => false,
         }
     }
 
-    /// clears the screen.
-    fn clear() -> Result<(), io::Error> {
-        io::stdout()
-            .execute(terminal::Clear(terminal::ClearType::All))
-            .map(drop)
+    /// returns `true` if the key press should switch to the next view.
+    fn is_next_view(key: &KeyEvent) -> bool {
+        key.kind == KeyEventKind::Press && matches!(key.code, KeyCode::Char('v') | KeyCode::Tab)
     }
 
-    // XXX(kate): this could be prettier.
-    fn border(cols: u16, rows: u16) -> Result<(), io::Error> {
-        let mut stdout = io::stdout();
-        for y in 0..rows {
-            for x in 0..cols {
-                if (y == 0 || y == rows - 1) || (x == 0 || x == cols - 1) {
-                    // in this loop we are more efficient by not flushing the buffer.
-                    stdout
-                        .queue(cursor::MoveTo(x, y))?
-                        .queue(style::PrintStyledContent("█".grey()))?;
+    /// returns how the key press should scroll the list of cpus, if it should.
+    fn scroll(key: &KeyEvent) -> Option<Scroll> {
+        if key.kind == KeyEventKind::Release {
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(Scroll::Up),
+            KeyCode::Down | KeyCode::Char('j') => Some(Scroll::Down),
+            KeyCode::PageUp => Some(Scroll::PageUp),
+            KeyCode::PageDown => Some(Scroll::PageDown),
+            KeyCode::Home => Some(Scroll::Top),
+            KeyCode::End => Some(Scroll::Bottom),
+            _ => None,
+        }
+    }
+}
+
+// === impl Window ===
+
+impl Window {
+    /// the rows above the cpus, holding the summary strips.
+    const HEADER: u16 = 5;
+    /// the space between the border and the window's contents.
+    const MARGIN: u16 = 2;
+    /// room for a label, such as `cpu12 100%`.
+    const LABEL: u16 = 10;
+    /// the space between a cpu's label, meter, and sparkline.
+    const GAP: u16 = 2;
+
+    /// returns a new, empty window of the given size.
+    fn new(cols: u16, rows: u16, options: &Options) -> Self {
+        Self {
+            screen: Screen::new(cols, rows),
+            history: VecDeque::new(),
+            known: BTreeSet::new(),
+            latest: None,
+            meter_width: options.meter_width,
+            meter_align: options.meter_align,
+            view: View::default(),
+            scroll: 0,
+        }
+    }
+
+    /// returns the position of the first cpu shown, and the number of cpus shown.
+    ///
+    /// when there are more cpus than rows, the last row is kept for a note of how many are
+    /// hidden.
+    fn shown(&self) -> (usize, usize) {
+        let rows = self.screen.rows();
+        let rows = usize::from(rows.saturating_sub(Self::HEADER + Self::MARGIN));
+        let cpus = self.known.len();
+        let shown = match cpus > rows {
+            true => rows.saturating_sub(1),
+            false => cpus,
+        };
+
+        (self.scroll.min(cpus - shown), shown)
+    }
+
+    /// returns the horizontal position of each cpu's meter.
+    fn meter_x(&self) -> u16 {
+        Self::MARGIN + Self::LABEL + Self::GAP
+    }
+
+    /// returns the horizontal position of each cpu's sparkline.
+    fn sparkline_x(&self) -> u16 {
+        let meter_width = u16::try_from(self.meter_width).unwrap_or(u16::MAX);
+        self.meter_x()
+            .saturating_add(meter_width)
+            .saturating_add(Self::GAP)
+    }
+
+    /// returns the width of each cpu's sparkline, in cells.
+    fn sparkline_width(&self) -> usize {
+        let end = self.screen.cols().saturating_sub(Self::MARGIN);
+        end.saturating_sub(self.sparkline_x()).into()
+    }
+
+    /// returns the number of past measurements that fit in the window.
+    fn capacity(&self) -> usize {
+        self.sparkline_width() * Sparkline::VALUES_PER_CELL
+    }
+
+    /// adds a recording to the window.
+    fn record(&mut self, recording: Recording) {
+        self.known
+            .extend(recording.cpus().keys().chain(recording.appeared()).copied());
+        self.history.push_back(recording.cpus().clone());
+        self.latest = Some(recording);
+        self.trim();
+    }
+
+    /// switches to the next view.
+    fn next_view(&mut self) {
+        self.view = self.view.next();
+    }
+
+    /// scrolls the list of cpus, if there are more than fit in the window.
+    fn scroll(&mut self, scroll: Scroll) {
+        let (first, shown) = self.shown();
+        let last = self.known.len() - shown;
+        self.scroll = match scroll {
+            Scroll::Up => first.saturating_sub(1),
+            Scroll::Down => first + 1,
+            Scroll::PageUp => first.saturating_sub(shown),
+            Scroll::PageDown => first + shown,
+            Scroll::Top => 0,
+            Scroll::Bottom => last,
+        }
+        .min(last);
+    }
+
+    /// changes the size of the window.
+    fn resize(&mut self, cols: u16, rows: u16) {
+        self.screen.resize(cols, rows);
+        self.trim();
+    }
+
+    /// drops past measurements that no longer fit in the window.
+    fn trim(&mut self) {
+        let capacity = self.capacity();
+        let excess = self.history.len().saturating_sub(capacity);
+        self.history.drain(..excess);
+    }
+
+    /// draws the window from scratch, without relying on what the terminal is showing.
+    fn redraw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        self.screen.invalidate();
+        self.draw(out)
+    }
+
+    /// draws the window, writing only what changed since it was last drawn.
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        self.paint();
+        self.screen.render(out)
+    }
+
+    /// paints the window onto the screen.
+    fn paint(&mut self) {
+        let (meter_x, sparkline_x) = (self.meter_x(), self.sparkline_x());
+        let (sparkline_width, capacity) = (self.sparkline_width(), self.capacity());
+        let (first, shown) = self.shown();
+        let Self {
+            screen,
+            history,
+            known,
+            latest,
+            meter_width,
+            meter_align,
+            view,
+            ..
+        } = self;
+
+        screen.clear();
+        Self::border(screen);
+
+        let Some(latest) = latest else {
+            return;
+        };
+
+        let summary = Self::summary(latest.rates(), latest.cores_busy());
+        screen.print(2, 2, &summary, Color::Grey);
+        screen.print(2, 3, &Self::states(latest.system()), Color::Grey);
+        if *view == View::Breakdown {
+            Self::legend(screen, meter_x, Self::HEADER - 1);
+        }
+
+        // cpus that do not fit are noted below the last row, and can be scrolled to.
+        let hidden = known.len() - shown;
+        if hidden > 0 {
+            let below = hidden - first;
+            let note = format!(
+                "+{hidden} more cpus: {first} above, {below} below (scroll with ↑ ↓ pgup pgdn)"
+            );
+            let y = Self::HEADER.saturating_add(u16::try_from(shown).unwrap_or(u16::MAX));
+            screen.print(Self::MARGIN, y, &note, Color::DarkGrey);
+        }
+
+        let rows = (Self::HEADER..).zip(known.iter().skip(first).take(shown));
+        for (y, cpu) in rows {
+            let (label, color) = match latest.cpus().get(cpu) {
+                Some(measurement) => (format!("{cpu} {}%", measurement.percentage()), Color::Grey),
+                None if latest.appeared().contains(cpu) => (cpu.to_string(), Color::Grey),
+                None => (cpu.to_string(), Color::DarkRed),
+            };
+            screen.print(Self::MARGIN, y, &label, color);
+
+            if *view == View::Breakdown {
+                let end = screen.cols().saturating_sub(Self::MARGIN);
+                let width = end.saturating_sub(meter_x).into();
+                if let Some(measurement) = latest.cpus().get(cpu) {
+                    Self::breakdown(screen, meter_x, y, measurement, width);
                 }
+                continue;
+            }
+
+            if let Some(measurement) = latest.cpus().get(cpu) {
+                let meter = Meter::new(Self::fraction(measurement), *meter_width)
+                    .with_align(*meter_align)
+                    .to_string();
+                screen.print(meter_x, y, &meter, Self::color(&[Some(measurement)]));
+            }
+
+            // pad the history, so that each cell's measurements line up with the sparkline.
+            let samples = std::iter::repeat_n(None, capacity.saturating_sub(history.len()))
+                .chain(history.iter().map(|cpus| cpus.get(cpu)))
+                .collect::<Vec<_>>();
+            let sparkline = Sparkline::new(
+                samples.iter().map(|m| m.map_or(0.0, Self::fraction)),
+                sparkline_width,
+            )
+            .to_string();
+            let cells = sparkline
+                .chars()
+                .zip(samples.chunks(Sparkline::VALUES_PER_CELL))
+                .zip(sparkline_x..);
+            for ((symbol, samples), x) in cells {
+                let color = Self::color(samples);
+                screen.set(x, y, Cell { symbol, color });
             }
         }
+    }
 
-        Ok(())
+    /// paints a legend of the states shown in the breakdown view.
+    fn legend(screen: &mut Screen, x: u16, y: u16) {
+        let mut x = x;
+        for state in Self::BREAKDOWN {
+            let name = state.name();
+            screen.print(x, y, name, Self::state_color(state));
+            x = x.saturating_add(name.len() as u16 + 1);
+        }
+    }
+
+    /// paints a bar `width` cells wide, divided into the share of time spent in each state.
+    fn breakdown(screen: &mut Screen, x: u16, y: u16, measurement: &Measurement, width: usize) {
+        let mut x = x;
+        for (state, cells) in Self::segments(measurement, width) {
+            let cell = Cell {
+                symbol: '█',
+                color: Self::state_color(state),
+            };
+            for _ in 0..cells {
+                screen.set(x, y, cell);
+                x = x.saturating_add(1);
+            }
+        }
+    }
+
+    /// the states shown in the breakdown view, in the order they are drawn.
+    const BREAKDOWN: [State; 9] = [
+        State::User,
+        State::Nice,
+        State::System,
+        State::Iowait,
+        State::Irq,
+        State::Softirq,
+        State::Steal,
+        State::Guest,
+        State::GuestNice,
+    ];
+
+    /// divides a bar `width` cells wide into the share of time spent in each busy state.
+    ///
+    /// the remainder of the bar, if any, is idle time. states that were not reported are
+    /// omitted.
+    fn segments(measurement: &Measurement, width: usize) -> Vec<(State, usize)> {
+        // round the running total, rather than each state, so that rounding errors do not add up.
+        let mut sum = 0.0;
+        let mut drawn = 0;
+        Self::BREAKDOWN
+            .into_iter()
+            .filter_map(|state| {
+                sum += measurement.fraction(state)?;
+                let end = ((sum * width as f64).round() as usize).min(width);
+                let cells = end - drawn;
+                drawn = end;
+                Some((state, cells))
+            })
+            .filter(|(_, cells)| *cells > 0)
+            .collect()
+    }
+
+    /// returns the color used to draw time spent in the given state.
+    fn state_color(state: State) -> Color {
+        match state {
+            State::User => Color::Green,
+            State::Nice => Color::Blue,
+            State::System => Color::Red,
+            State::Idle => Color::DarkGrey,
+            State::Iowait => Color::Grey,
+            State::Irq => Color::Yellow,
+            State::Softirq => Color::Magenta,
+            State::Steal => Color::Cyan,
+            State::Guest => Color::DarkCyan,
+            State::GuestNice => Color::DarkBlue,
+        }
+    }
+
+    /// returns the fraction of time that a cpu was busy.
+    ///
+    /// an interval in which no time passed is drawn as idle.
+    fn fraction(measurement: &Measurement) -> f64 {
+        measurement.ratio().unwrap_or(0.0)
+    }
+
+    /// returns the color used to draw the given measurements.
+    fn color(measurements: &[Option<&Measurement>]) -> Color {
+        let mut present = measurements.iter().flatten().peekable();
+        if present.peek().is_none() {
+            // the cpu was offline.
+            Color::DarkGrey
+        } else if present.all(|m| m.is_trustworthy()) {
+            Color::Green
+        } else {
+            // a counter went backwards during this measurement.
+            Color::DarkYellow
+        }
+    }
+
+    /// formats a summary of system-wide activity, such as context switches and forks.
+    fn summary(rates: &Rates, cores_busy: Option<f64>) -> String {
+        let rate = |r: Option<f64>| r.map_or_else(|| "-".to_owned(), |r| format!("{r:.0}"));
+        let count = |c: Option<u64>| c.map_or_else(|| "-".to_owned(), |c| c.to_string());
+
+        format!(
+            "busy {:>6} cores  ctxt/s {:>8}  forks/s {:>6}  intr/s {:>8}  softirq/s {:>8}  running {:>4}  blocked {:>4}",
+            cores_busy.map_or_else(|| "-".to_owned(), |c| format!("{c:.2}")),
+            rate(rates.context_switches()),
+            rate(rates.forks()),
+            rate(rates.interrupts()),
+            rate(rates.softirqs()),
+            count(rates.procs_running()),
+            count(rates.procs_blocked()),
+        )
+    }
+
+    /// formats the share of time spent in states that older kernels may not report.
+    ///
+    /// states that were not reported, or intervals in which no time passed, are shown as absent
+    /// rather than as zero.
+    fn states(system: &Measurement) -> String {
+        let share = |state: State| {
+            system
+                .fraction(state)
+                .map_or_else(|| "-".to_owned(), |f| format!("{:.0}%", f * 100.0))
+        };
+
+        format!(
+            "iowait {:>4}  irq {:>4}  softirq {:>4}  steal {:>4}  guest {:>4}  guest_nice {:>4}",
+            share(State::Iowait),
+            share(State::Irq),
+            share(State::Softirq),
+            share(State::Steal),
+            share(State::Guest),
+            share(State::GuestNice),
+        )
+    }
+
+    /// paints a border around the edge of the screen.
+    fn border(screen: &mut Screen) {
+        let (cols, rows) = (screen.cols(), screen.rows());
+        let edge = Cell {
+            symbol: '█',
+            color: Color::Grey,
+        };
+
+        for x in 0..cols {
+            screen.set(x, 0, edge);
+            screen.set(x, rows.saturating_sub(1), edge);
+        }
+        for y in 0..rows {
+            screen.set(0, y, edge);
+            screen.set(cols.saturating_sub(1), y, edge);
+        }
+    }
+}
+
+// === impl View ===
+
+impl View {
+    /// returns the view after this one.
+    fn next(self) -> Self {
+        match self {
+            Self::Sparklines => Self::Breakdown,
+            Self::Breakdown => Self::Sparklines,
+        }
     }
 }
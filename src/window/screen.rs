@@ -0,0 +1,152 @@
+use {
+    crossterm::{
+        QueueableCommand, cursor,
+        style::{self, Color},
+    },
+    std::io::{self, Write},
+};
+
+/// an in-memory copy of the terminal's contents.
+///
+/// each frame is drawn into a buffer, and [`Screen::render()`] compares it to the previous frame,
+/// writing only the cells that changed.
+pub(super) struct Screen {
+    /// the width of the screen.
+    cols: u16,
+    /// the height of the screen.
+    rows: u16,
+    /// the frame being drawn, in row-major order.
+    frame: Vec<Cell>,
+    /// the frame currently shown by the terminal, or `None` if its contents are unknown.
+    shown: Option<Vec<Cell>>,
+}
+
+/// a single character cell.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct Cell {
+    /// the character in this cell.
+    pub(super) symbol: char,
+    /// the color of the character.
+    pub(super) color: Color,
+}
+
+// === impl Screen ===
+
+impl Screen {
+    /// returns a new, blank screen of the given size.
+    pub(super) fn new(cols: u16, rows: u16) -> Self {
+        Self {
+            cols,
+            rows,
+            frame: vec![Cell::BLANK; usize::from(cols) * usize::from(rows)],
+            shown: None,
+        }
+    }
+
+    /// returns the width of the screen.
+    pub(super) fn cols(&self) -> u16 {
+        self.cols
+    }
+
+    /// returns the height of the screen.
+    pub(super) fn rows(&self) -> u16 {
+        self.rows
+    }
+
+    /// changes the size of the screen.
+    ///
+    /// the next frame is rendered in full, because the terminal may have rearranged its contents.
+    pub(super) fn resize(&mut self, cols: u16, rows: u16) {
+        *self = Self::new(cols, rows);
+    }
+
+    /// forgets what the terminal is showing, so that the next frame is rendered in full.
+    pub(super) fn invalidate(&mut self) {
+        self.shown = None;
+    }
+
+    /// blanks the frame being drawn.
+    pub(super) fn clear(&mut self) {
+        self.frame.fill(Cell::BLANK);
+    }
+
+    /// returns the cell at the given position, if it is on the screen.
+    #[cfg(test)]
+    pub(super) fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+        self.index(x, y).map(|i| &self.frame[i])
+    }
+
+    /// sets the cell at the given position.
+    ///
+    /// cells outside of the screen are ignored.
+    pub(super) fn set(&mut self, x: u16, y: u16, cell: Cell) {
+        if let Some(i) = self.index(x, y) {
+            self.frame[i] = cell;
+        }
+    }
+
+    /// writes `text` starting at the given position.
+    ///
+    /// text that runs past the right edge of the screen is cut off.
+    pub(super) fn print(&mut self, x: u16, y: u16, text: &str, color: Color) {
+        for (x, symbol) in (x..self.cols).zip(text.chars()) {
+            self.set(x, y, Cell { symbol, color });
+        }
+    }
+
+    /// writes the cells that changed since the last frame to `out`.
+    pub(super) fn render(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let Self {
+            cols, frame, shown, ..
+        } = self;
+
+        // where the terminal's cursor is, and which color it is drawing with.
+        let mut position = None;
+        let mut color = None;
+
+        for (i, cell) in frame.iter().enumerate() {
+            if shown.as_ref().is_some_and(|shown| shown[i] == *cell) {
+                continue;
+            }
+
+            let x = (i % usize::from(*cols)) as u16;
+            let y = (i / usize::from(*cols)) as u16;
+            if position != Some((x, y)) {
+                out.queue(cursor::MoveTo(x, y))?;
+            }
+            if color != Some(cell.color) {
+                out.queue(style::SetForegroundColor(cell.color))?;
+                color = Some(cell.color);
+            }
+            out.queue(style::Print(cell.symbol))?;
+            position = Some((x + 1, y));
+        }
+
+        if color.is_some() {
+            out.queue(style::ResetColor)?;
+        }
+
+        match shown {
+            Some(shown) => shown.copy_from_slice(frame),
+            None => *shown = Some(frame.clone()),
+        }
+
+        out.flush()
+    }
+
+    /// returns the index of the cell at the given position, if it is on the screen.
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        (x < self.cols && y < self.rows)
+            .then(|| usize::from(y) * usize::from(self.cols) + usize::from(x))
+    }
+}
+
+// === impl Cell ===
+
+impl Cell {
+    /// an empty cell.
+    pub(super) const BLANK: Self = Self {
+        symbol: ' ',
+        color: Color::Reset,
+    };
+}
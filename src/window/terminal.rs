@@ -0,0 +1,74 @@
+use {
+    crossterm::{cursor, execute, terminal},
+    std::{
+        io,
+        sync::{
+            Once,
+            atomic::{AtomicBool, Ordering},
+        },
+    },
+};
+
+/// a guard that owns the terminal while the tui is running.
+///
+/// this switches to the alternate screen and enables raw mode when it is created, and restores
+/// the terminal when it is dropped. the terminal is also restored if the application panics.
+pub(super) struct Terminal {
+    _private: (),
+}
+
+/// whether the terminal is currently owned by a [`Terminal`].
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// === impl Terminal ===
+
+impl Terminal {
+    /// takes over the terminal.
+    pub(super) fn enter() -> io::Result<Self> {
+        Self::install_panic_hook();
+
+        terminal::enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        // from here on, dropping the guard will restore the terminal.
+        let guard = Self { _private: () };
+
+        execute!(
+            io::stdout(),
+            terminal::EnterAlternateScreen,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::Hide,
+        )?;
+
+        Ok(guard)
+    }
+
+    /// restores the terminal, if it is currently owned by a [`Terminal`].
+    fn restore() -> io::Result<()> {
+        if !ACTIVE.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        // raw mode is left even if the screen cannot be restored, as when stdout is closed.
+        let shown = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let raw = terminal::disable_raw_mode();
+        shown.and(raw)
+    }
+
+    /// restores the terminal before a panic message is printed.
+    fn install_panic_hook() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                let _ = Self::restore();
+                hook(info);
+            }));
+        });
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = Self::restore();
+    }
+}
@@ -0,0 +1,219 @@
+use {
+    super::*,
+    crate::{
+        source::mock::{MockStatClock, MockStatFile},
+        stat::Entry,
+    },
+    std::time::Duration,
+};
+
+/// returns the text of row `y` of the screen.
+fn row(screen: &Screen, y: u16) -> String {
+    (0..screen.cols())
+        .map(|x| screen.get(x, y).unwrap().symbol)
+        .collect()
+}
+
+mod screen_tests {
+    use super::*;
+
+    /// renders the screen, returning what was written to the terminal.
+    fn render(screen: &mut Screen) -> Vec<u8> {
+        let mut out = Vec::new();
+        screen.render(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn first_frame_is_rendered_in_full() {
+        let mut screen = Screen::new(4, 2);
+        let out = String::from_utf8(render(&mut screen)).unwrap();
+        assert_eq!(out.matches(' ').count(), 8);
+    }
+
+    #[test]
+    fn unchanged_frame_writes_nothing() {
+        let mut screen = Screen::new(8, 2);
+        screen.print(1, 1, "cpu0", Color::Grey);
+        render(&mut screen);
+
+        screen.clear();
+        screen.print(1, 1, "cpu0", Color::Grey);
+        assert!(render(&mut screen).is_empty());
+    }
+
+    #[test]
+    fn only_changed_cells_are_written() {
+        let mut screen = Screen::new(8, 2);
+        screen.print(0, 0, "42", Color::Green);
+        render(&mut screen);
+
+        screen.clear();
+        screen.print(0, 0, "47", Color::Green);
+        let out = String::from_utf8(render(&mut screen)).unwrap();
+        assert!(out.contains('7'));
+        assert!(!out.contains('4'));
+    }
+
+    /// a shorter value must not leave the tail of a longer one behind.
+    #[test]
+    fn stale_digits_are_cleared() {
+        let mut screen = Screen::new(8, 1);
+        screen.print(0, 0, "100", Color::Green);
+        render(&mut screen);
+
+        screen.clear();
+        screen.print(0, 0, "7", Color::Green);
+        render(&mut screen);
+        assert_eq!(row(&screen, 0), "7       ");
+    }
+
+    #[test]
+    fn text_is_cut_off_at_the_edge() {
+        let mut screen = Screen::new(4, 1);
+        screen.print(2, 0, "cpu0", Color::Grey);
+        assert_eq!(row(&screen, 0), "  cp");
+    }
+
+    #[test]
+    fn invalidate_renders_in_full() {
+        let mut screen = Screen::new(4, 1);
+        render(&mut screen);
+        assert!(render(&mut screen).is_empty());
+
+        screen.invalidate();
+        assert!(!render(&mut screen).is_empty());
+    }
+
+    #[test]
+    fn resize_renders_in_full() {
+        let mut screen = Screen::new(4, 1);
+        render(&mut screen);
+
+        screen.resize(6, 2);
+        let out = String::from_utf8(render(&mut screen)).unwrap();
+        assert_eq!(out.matches(' ').count(), 12);
+    }
+}
+
+mod breakdown_tests {
+    use super::*;
+
+    fn measurement(a: &str, b: &str) -> Measurement {
+        let time = |line: &str| match line.parse::<Entry>().unwrap() {
+            Entry::AllCpu { time } | Entry::Cpu { time, .. } => time,
+            _other => panic!(),
+        };
+        Measurement::new(time(a), time(b))
+    }
+
+    #[test]
+    fn segments() {
+        let measurement = measurement("cpu 0 0 0 0 0 0 0", "cpu 50 0 25 25 0 0 0");
+        assert_eq!(
+            Window::segments(&measurement, 8),
+            [(State::User, 4), (State::System, 2)]
+        );
+    }
+
+    /// rounding each state on its own would draw a bar wider than it should be.
+    #[test]
+    fn rounding_does_not_accumulate() {
+        let measurement = measurement("cpu 0 0 0 0 0 0 0", "cpu 1 1 1 0 0 0 0");
+        let segments = Window::segments(&measurement, 10);
+        assert_eq!(segments.iter().map(|(_, n)| n).sum::<usize>(), 10);
+    }
+
+    #[test]
+    fn unreported_states_are_omitted() {
+        let measurement = measurement("cpu 0 0 0 0", "cpu 10 0 0 10");
+        assert_eq!(Window::segments(&measurement, 4), [(State::User, 2)]);
+    }
+
+    #[test]
+    fn idle() {
+        let measurement = measurement("cpu 0 0 0 0", "cpu 0 0 0 0");
+        assert!(Window::segments(&measurement, 4).is_empty());
+    }
+}
+
+mod scroll_tests {
+    use super::*;
+
+    /// returns a window of 20 rows, that has recorded `cpus` cpus.
+    fn window(cpus: u32) -> Window {
+        let stats = [100, 200].map(|ticks| MockStatFile::render(0..cpus, ticks));
+        let clock = MockStatClock::ticking(Duration::from_secs(1), 2);
+        let mut sentinel = Sentinel::from_parts(clock, MockStatFile::new(stats));
+        assert!(sentinel.observe().unwrap().is_none());
+
+        let mut window = Window::new(80, 20, &Options::default());
+        window.record(sentinel.observe().unwrap().unwrap());
+        window.paint();
+        window
+    }
+
+    /// returns the rows of the window that mention `text`.
+    fn rows(window: &Window, text: &str) -> Vec<u16> {
+        (0..window.screen.rows())
+            .filter(|&y| row(&window.screen, y).contains(text))
+            .collect()
+    }
+
+    #[test]
+    fn all_cpus_fit() {
+        let window = window(4);
+        assert_eq!(rows(&window, "cpu3 "), [Window::HEADER + 3]);
+        assert!(rows(&window, "more cpus").is_empty());
+    }
+
+    /// cpus that do not fit are not drawn, but are noted on the last row.
+    #[test]
+    fn hidden_cpus_are_noted() {
+        let window = window(100);
+        assert_eq!(rows(&window, "cpu0 "), [Window::HEADER]);
+        assert_eq!(rows(&window, "cpu11 "), [Window::HEADER + 11]);
+        assert!(rows(&window, "cpu12 ").is_empty());
+        assert!(
+            row(&window.screen, Window::HEADER + 12).contains("+88 more cpus: 0 above, 88 below")
+        );
+    }
+
+    #[test]
+    fn scroll() {
+        let mut window = window(100);
+        window.scroll(Scroll::Down);
+        window.paint();
+        assert_eq!(rows(&window, "cpu1 "), [Window::HEADER]);
+        assert!(rows(&window, "cpu0 ").is_empty());
+
+        window.scroll(Scroll::PageDown);
+        window.paint();
+        assert_eq!(rows(&window, "cpu13 "), [Window::HEADER]);
+
+        window.scroll(Scroll::PageUp);
+        window.scroll(Scroll::Up);
+        window.scroll(Scroll::Up);
+        window.paint();
+        assert_eq!(rows(&window, "cpu0 "), [Window::HEADER]);
+
+        // scrolling stops at the last cpu.
+        window.scroll(Scroll::Bottom);
+        window.scroll(Scroll::Down);
+        window.paint();
+        assert_eq!(rows(&window, "cpu88 "), [Window::HEADER]);
+        assert_eq!(rows(&window, "cpu99 "), [Window::HEADER + 11]);
+        assert_eq!(rows(&window, "88 above, 0 below").len(), 1);
+    }
+
+    /// a window that grows to fit every cpu shows them all, wherever it was scrolled to.
+    #[test]
+    fn resize() {
+        let mut window = window(16);
+        window.scroll(Scroll::Bottom);
+        window.resize(80, 40);
+        window.paint();
+        assert_eq!(rows(&window, "cpu0 "), [Window::HEADER]);
+        assert!(rows(&window, "more cpus").is_empty());
+    }
+}